@@ -0,0 +1,38 @@
+// Compares `inspect` with `InspectCache::inspect` on the same queries inspected
+// again and again. Run with `cargo bench`.
+use sql_inspector::{inspect, InspectCache};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const QUERIES: [&str; 3] = [
+    "SELECT u.id, u.name, o.total FROM users u JOIN orders o ON o.user_id = u.id WHERE u.age > $1",
+    "INSERT INTO users (id, name) VALUES ($1, $2) ON CONFLICT (id) DO UPDATE SET name = excluded.name",
+    "UPDATE orders SET status = 'shipped' WHERE id IN (SELECT order_id FROM shipments WHERE sent)",
+];
+const ROUNDS: usize = 10_000;
+
+fn time(mut run: impl FnMut(&str)) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for sql in QUERIES {
+            run(sql);
+        }
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let uncached = time(|sql| {
+        black_box(inspect(sql, "postgres").unwrap());
+    });
+    let mut cache = InspectCache::new("postgres", QUERIES.len());
+    let cached = time(|sql| {
+        black_box(cache.inspect(sql).unwrap());
+    });
+    println!("inspect:               {uncached:?}");
+    println!("InspectCache::inspect: {cached:?}");
+    println!(
+        "speedup: {:.1}x",
+        uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+}
@@ -1,15 +1,46 @@
 use core::ops::ControlFlow;
+use indexmap::IndexSet;
+use js_sys::{Array, JsString, Map, Number, Object, Reflect};
 use serde::{Deserialize, Serialize};
 use sqlparser::ast::Visitor;
 use sqlparser::ast::*;
-use sqlparser::dialect::GenericDialect;
-use sqlparser::parser::Parser;
-use std::collections::{HashMap, HashSet};
+use sqlparser::dialect::{
+    AnsiDialect, GenericDialect, MsSqlDialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect,
+    SnowflakeDialect,
+};
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Location, Span, Token, TokenWithSpan, Tokenizer};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen(typescript_custom_section)]
 const TS_APPEND_CONTENT: &'static str = r#"
+/**
+ * The type of a statement.
+ */
+export type QueryType =
+  | 'SELECT'
+  | 'INSERT'
+  | 'UPDATE'
+  | 'DELETE'
+  | 'CREATE_VIEW'
+  | 'LOCK'
+  | 'REFRESH'
+  | 'CALL'
+  | 'CREATE_TRIGGER'
+  | 'MERGE'
+  | 'CREATE_TABLE'
+  | 'ALTER_TABLE'
+  | 'DROP'
+  | 'UNSUPPORTED';
+
+/**
+ * Whether a statement only reads data.
+ */
+export type Access = 'READ' | 'WRITE';
+
 /**
  * The result of parsing a SQL query, containing information about tables and columns.
  */
@@ -28,25 +59,418 @@ export interface ExtractResult {
   /**
    * Type of SQL operation.
    */
-  query_type: 'SELECT' | 'INSERT' | 'UPDATE' | 'DELETE';
+  query_type: QueryType;
 
   /**
    * The primary table being modified (for INSERT/UPDATE operations).
    * Empty string for SELECT/DELETE operations.
    */
   target_table: string;
+
+  /**
+   * The SQL text of the statement, only present when requested
+   * with `include_source` in batch mode.
+   */
+  source?: string;
+
+  /**
+   * The row locking clause of a SELECT, e.g. "FOR UPDATE", "FOR NO KEY UPDATE",
+   * "FOR SHARE" or "FOR KEY SHARE" (the strongest one).
+   */
+  locking?: string;
+
+  /**
+   * The tables listed in the `OF` targets of the locking clause.
+   */
+  locked_tables?: string[];
+
+  /**
+   * The database qualifiers of the referenced tables (e.g. "db1" for "db1.public.users",
+   * and for "db1.users" in MySQL).
+   */
+  databases?: string[];
+
+  /**
+   * True when the referenced tables span more than one database.
+   */
+  cross_database?: boolean;
+
+  /**
+   * True when the statement creates a materialized view.
+   */
+  materialized?: boolean;
+
+  /**
+   * The number of rows requested with LIMIT or FETCH FIRST, when it's a literal.
+   */
+  limit?: number;
+
+  /**
+   * True when the query has a LIMIT or FETCH FIRST, even when `limit` is missing
+   * because the row count is not a literal (e.g. `LIMIT $1`).
+   */
+  has_limit?: boolean;
+
+  /**
+   * The number of rows skipped with OFFSET, when it's a literal.
+   */
+  offset?: number;
+
+  /**
+   * The columns that appear more than once in the SELECT projection.
+   */
+  duplicate_projections?: string[];
+
+  /**
+   * True for `INSERT OVERWRITE` statements, which replace the table content.
+   */
+  overwrite?: boolean;
+
+  /**
+   * True for an upsert, i.e. an INSERT with `ON CONFLICT` or `ON DUPLICATE KEY UPDATE`.
+   */
+  has_upsert?: boolean;
+
+  /**
+   * Heuristic telling whether the statement can be safely run again (e.g. retried).
+   * SELECT and DELETE are idempotent, UPDATE is when no assigned value depends on
+   * columns, INSERT only when it overwrites or handles conflicts.
+   */
+  idempotent: boolean;
+
+  /**
+   * "READ" for a SELECT without locking or INTO clause, "WRITE" otherwise
+   * (INSERT, UPDATE, DELETE, SELECT ... FOR UPDATE, SELECT ... INTO, DDL, ...).
+   */
+  access: Access;
+
+  /**
+   * The tables locked by `LOCK TABLES` (MySQL), as [table, lock mode] pairs.
+   */
+  locks?: [string, string][];
+
+  /**
+   * The deepest subquery/CTE nesting level: 0 for a flat query, 2 for a subquery in a subquery.
+   */
+  max_depth: number;
+
+  /**
+   * The stored procedures invoked with CALL.
+   */
+  procedures?: string[];
+
+  /**
+   * The literal values the columns are compared with, as [column, value] pairs
+   * (e.g. ["age", "18"] for `age > 18`), with the value rendered as SQL.
+   */
+  predicate_values?: [string, string][];
+
+  /**
+   * The number of leaf predicates in the WHERE clauses, splitting on AND/OR:
+   * 3 for `WHERE a = 1 AND (b > 2 OR c < 3)`.
+   */
+  predicate_count: number;
+
+  /**
+   * The sequences used with the Postgres `nextval`, `currval` and `setval` functions.
+   */
+  sequences?: string[];
+
+  /**
+   * The caveats of the extraction, e.g. the unqualified columns of a query with
+   * many tables, which can't be resolved without the schema.
+   */
+  warnings?: Warning[];
+
+  /**
+   * Heuristic: true when a projection mixes bare columns and aggregates without
+   * a GROUP BY (e.g. `SELECT dept, COUNT(*) FROM employees`), invalid in strict SQL modes.
+   */
+  invalid_aggregation?: boolean;
+
+  /**
+   * The data types declared by CREATE TABLE, by `table.column` (e.g. "VARCHAR(255)").
+   */
+  column_types?: Map<string, string>;
+
+  /**
+   * The table aliases, with the table they refer to (e.g. "u" -> "users"),
+   * following the aliases of aliases.
+   */
+  alias_map?: Map<string, string>;
+
+  /**
+   * The GROUP BY keys: the columns, and the other expressions rendered as SQL
+   * (e.g. "DATE_TRUNC('month', created_at)").
+   */
+  group_by?: string[];
+
+  /**
+   * The names of the common table expressions, which are not listed in `tables`.
+   */
+  ctes?: string[];
+
+  /**
+   * The CTEs reading other CTEs of the same WITH, as [cte, dependency] pairs
+   * (e.g. ["b", "a"] for `WITH a AS (...), b AS (SELECT * FROM a) ...`).
+   */
+  cte_dependencies?: [string, string][];
+
+  /**
+   * The ORDER BY keys, in order.
+   */
+  order_by?: OrderByColumn[];
+
+  /**
+   * The joins of the queries, with their type and the columns of their condition.
+   */
+  joins?: JoinInfo[];
+
+  /**
+   * The JSON_TABLE (MySQL) and OPENJSON (MsSql) table functions, with the columns they define.
+   */
+  json_tables?: JsonTable[];
+
+  /**
+   * The schema of the INSERT, UPDATE or DELETE target, when qualified
+   * (e.g. "tenant_42" for `INSERT INTO tenant_42.orders ...`).
+   */
+  target_schema?: string;
+
+  /**
+   * The columns of the updated table referenced by the subqueries of the UPDATE values
+   * (e.g. "t.id" for `UPDATE t SET n = (SELECT COUNT(*) FROM x WHERE x.t_id = t.id)`).
+   */
+  correlated_columns?: string[];
+
+  /**
+   * The functions called, uppercased (e.g. "COUNT", "DATE_TRUNC").
+   */
+  functions?: string[];
+
+  /**
+   * The window functions (called with OVER), uppercased (e.g. "ROW_NUMBER", "SUM").
+   */
+  window_functions?: string[];
+
+  /**
+   * The RETURNING items of an INSERT, UPDATE or DELETE, in order: the columns,
+   * "*", or the other expressions rendered as SQL.
+   */
+  returning?: string[];
+
+  /**
+   * The base tables written through a view targeted by an INSERT, UPDATE or DELETE.
+   */
+  resolved_targets?: string[];
+
+  /**
+   * The unqualified columns that can't be attributed to a table, as their query
+   * reads many tables (e.g. "address" for "SELECT address FROM t1 JOIN t2 ON ...").
+   * They are listed in `columns` as well.
+   */
+  unresolved_columns?: string[];
+
+  /**
+   * The schemas of the qualified tables: "public" for `public.orders`
+   * and for `sales.public.orders`.
+   */
+  schemas?: string[];
+
+  /**
+   * A sortable complexity score of the statement:
+   * 2 * joins + predicates + 3 * max_depth + 2 * subqueries + function calls.
+   */
+  complexity_score: number;
+
+  /**
+   * True for `SELECT DISTINCT` and `SELECT DISTINCT ON (...)`.
+   */
+  distinct?: boolean;
+
+  /**
+   * The `DISTINCT ON` expressions, in order: the columns, and the other
+   * expressions rendered as SQL.
+   */
+  distinct_on?: string[];
+
+  /**
+   * The columns of the WHERE clauses (of the subqueries too), e.g. to suggest indexes.
+   */
+  filter_columns?: string[];
+
+  /**
+   * The columns of the HAVING clauses, the ones inside the aggregate functions too
+   * (e.g. `orders.id` for `HAVING COUNT(orders.id) > 5`).
+   */
+  having_columns?: string[];
+
+  /**
+   * The aliases of the derived tables (the subqueries in FROM), e.g. `sub` for
+   * `FROM (SELECT ...) sub`. They are not listed in `tables`.
+   */
+  derived_tables?: string[];
+
+  /**
+   * The columns of an INSERT ... SELECT, as [target column, source] pairs in order:
+   * the source column, or the expression rendered as SQL.
+   */
+  lineage?: [string, string][];
+
+  /**
+   * The kind of the objects dropped by a DROP statement, e.g. "TABLE", "VIEW" or "INDEX".
+   */
+  object_type?: string;
+
+  /**
+   * The bind parameter placeholders (e.g. "$1", "?", ":name"), in order and
+   * with duplicates, one per parameter.
+   */
+  parameters?: string[];
+}
+
+/**
+ * A join between two tables. The comma-separated tables of a FROM
+ * are CROSS joins, like CROSS JOIN they have no condition columns.
+ */
+export interface JoinInfo {
+  left_table: string;
+  right_table: string;
+  join_type:
+    | 'INNER'
+    | 'LEFT'
+    | 'RIGHT'
+    | 'FULL'
+    | 'CROSS'
+    | 'SEMI'
+    | 'ANTI'
+    | 'CROSS_APPLY'
+    | 'OUTER_APPLY'
+    | 'AS_OF';
+  /**
+   * The columns of the ON condition, or the USING columns.
+   */
+  columns: string[];
+}
+
+/**
+ * A JSON_TABLE or OPENJSON table function.
+ */
+export interface JsonTable {
+  alias?: string;
+  /**
+   * The JSON document: the column (e.g. "docs.body"), or the expression rendered as SQL.
+   */
+  source: string;
+  /**
+   * The columns defined by the function, in order.
+   */
+  columns: string[];
+}
+
+/**
+ * An ORDER BY key.
+ */
+export interface OrderByColumn {
+  /**
+   * The column, the expression rendered as SQL, or the position (e.g. "1").
+   */
+  column: string;
+
+  /**
+   * True for ASC, false for DESC, missing when the direction is not specified.
+   */
+  asc?: boolean;
+
+  /**
+   * True when the key is a position in the projection (`ORDER BY 1`).
+   */
+  ordinal?: boolean;
 }
 
+/**
+ * A caveat of the extraction.
+ */
+export type Warning =
+  | { kind: 'AmbiguousColumn'; detail: string }
+  | { kind: 'UnresolvedPosition'; detail: number }
+  | { kind: 'CircularView'; detail: string }
+  | { kind: 'DeprecatedSyntax'; detail: string }
+  | { kind: 'UnexpandedWildcard'; detail: string };
+
 /**
  * Parses a SQL query string and returns information about referenced tables and columns.
  * 
- * Supports SELECT, INSERT, UPDATE, and DELETE statements.
- * Does not support DDL statements like CREATE TABLE.
+ * Supports SELECT, INSERT, UPDATE, DELETE and MERGE statements, and some
+ * DDL statements (CREATE TABLE, ALTER TABLE, DROP, CREATE VIEW, CREATE TRIGGER).
  * 
  * @param sql - The SQL query to analyze
- * @returns Information about tables, columns, and query type
+ * @param dialect - The SQL dialect of the query, "generic" by default.
+ * Unknown dialects fall back to "generic".
+ * @param options - The output options
+ * @returns Information about tables, columns, and query type, or the error
+ * when the query can't be parsed
+ */
+export function sqlinspector(
+  sql: string,
+  dialect?: 'generic' | 'postgres' | 'mysql' | 'sqlite' | 'mssql' | 'ansi' | 'snowflake',
+  options?: OutputOptions
+): ExtractResult | InspectErrorResult;
+
+/**
+ * The options of the returned objects.
+ */
+export interface OutputOptions {
+  /**
+   * Use camelCase keys (e.g. `targetTable`, `queryType`) instead of the
+   * snake_case ones described by the types. Off by default.
+   */
+  camelCase?: boolean;
+}
+
+/**
+ * The error returned in place of a result when a query can't be inspected.
+ */
+export interface InspectErrorResult {
+  error: {
+    message: string;
+    /**
+     * The SQL snippet where the parser failed, for parse errors.
+     */
+    near?: string;
+  };
+}
+
+/**
+ * Like `sqlinspector`, but the query must be a single statement:
+ * an error is returned when it has more than one, or can't be parsed.
+ *
+ * @param sql - The SQL statement to analyze
+ * @param dialect - The SQL dialect of the statement, "generic" by default.
+ * Unknown dialects fall back to "generic".
+ * @param options - The output options
+ * @returns The result or the error
+ */
+export function sqlinspector_single(
+  sql: string,
+  dialect?: 'generic' | 'postgres' | 'mysql' | 'sqlite' | 'mssql' | 'ansi' | 'snowflake',
+  options?: OutputOptions
+): ExtractResult | InspectErrorResult;
+
+/**
+ * Inspects a batch of SQL queries with a single call.
+ *
+ * @param sqls - The SQL queries to analyze
+ * @param dialect - The SQL dialect of the queries, "generic" by default.
+ * Unknown dialects fall back to "generic".
+ * @param options - The output options
+ * @returns One item per query, in the same order: the result or the error
  */
-export function sqlinspector(sql: string): ExtractResult;
+export function sqlinspector_batch(
+  sqls: string[],
+  dialect?: 'generic' | 'postgres' | 'mysql' | 'sqlite' | 'mssql' | 'ansi' | 'snowflake',
+  options?: OutputOptions
+): (ExtractResult | InspectErrorResult)[];
 "#;
 
 // This extracts the columns and tables from a SQL query
@@ -60,516 +484,6493 @@ export function sqlinspector(sql: string): ExtractResult;
 // This query is ambiguous, because we don't know if the `address` and `name` columns are
 // from table1 or table2. We can't resolve this without the actual DB schema.
 
+// Serialized as a string: its TypeScript type is declared in `TS_APPEND_CONTENT`,
+// like the result's
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
-#[allow(clippy::upper_case_acronyms)]
-#[wasm_bindgen]
+#[allow(clippy::upper_case_acronyms, non_camel_case_types)]
 pub enum QueryType {
     #[default]
     SELECT,
     INSERT,
     UPDATE,
     DELETE,
+    CREATE_VIEW,
+    LOCK,
+    REFRESH,
+    CALL,
+    CREATE_TRIGGER,
+    MERGE,
+    CREATE_TABLE,
+    ALTER_TABLE,
+    DROP,
+    // A statement the inspector doesn't handle, e.g. `TRUNCATE` or `GRANT`
+    UNSUPPORTED,
+}
+
+// Whether a statement only reads data, e.g. to route it to a read replica.
+// The DDL statements (e.g. `CREATE VIEW`) are writes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Access {
+    Read,
+    Write,
+}
+
+impl Access {
+    // A SELECT with a row locking clause (`FOR UPDATE`, `FOR SHARE`) or creating a
+    // table (`SELECT ... INTO`) is a write: it can't run in a read-only transaction,
+    // e.g. on a replica. So are the unsupported statements, as they may write (e.g. `TRUNCATE`).
+    fn of(query_type: &QueryType, writes: bool) -> Self {
+        match query_type {
+            QueryType::SELECT if !writes => Access::Read,
+            _ => Access::Write,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// The lists are sorted, so the serialized result is stable across runs
+// (`tables` and `columns` keep the query order with `InspectOptions::ordered`).
+// Map fields must keep this property too, e.g. with a `BTreeMap`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtractResult {
-    tables: Vec<String>,
-    columns: Vec<String>,
-    target_table: String, // This is the target table in the INSERT, UPDATE or DELETE statements case
-    query_type: QueryType,
+    pub tables: Vec<String>,
+    pub columns: Vec<String>,
+    pub target_table: String, // This is the target table in the INSERT, UPDATE or DELETE statements case
+    pub query_type: QueryType,
+    // The statement text, only set when `InspectOptions::include_source` is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    // The row locking clause of a SELECT (`FOR UPDATE`, `FOR NO KEY UPDATE`, `FOR SHARE`,
+    // `FOR KEY SHARE`), the strongest one when there are many
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locking: Option<String>,
+    // The `OF` targets of the locking clause
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locked_tables: Vec<String>,
+    // The database qualifiers of the referenced tables (`db` in `db.schema.table`,
+    // and in `db.table` with MySQL)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub databases: Vec<String>,
+    // True when the referenced tables span more than one database
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub cross_database: bool,
+    // True for `CREATE MATERIALIZED VIEW`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub materialized: bool,
+    // The LIMIT (or FETCH) row count, when it's a literal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    // The OFFSET row count, when it's a literal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    // True when the query has a LIMIT (or FETCH), even when its row count is
+    // not a literal (e.g. `LIMIT $1`) and `limit` is missing
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub has_limit: bool,
+    // The columns projected more than once, e.g. `id` in `SELECT id, id`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_projections: Vec<String>,
+    // True for `INSERT OVERWRITE`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub overwrite: bool,
+    // True for an upsert: `INSERT ... ON CONFLICT` or `ON DUPLICATE KEY UPDATE`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub has_upsert: bool,
+    // Heuristic: true when running the statement again has no further effect.
+    // SELECT and DELETE are idempotent, UPDATE is when every assigned value is
+    // independent from the columns (`SET a = 1` but not `SET n = n + 1`), INSERT only
+    // when it overwrites or handles conflicts (upsert). Volatile functions
+    // (e.g. `now()`, `random()`) and triggers are not taken into account.
+    pub idempotent: bool,
+    // `READ` for the SELECT statements, `WRITE` for the others
+    pub access: Access,
+    // The tables locked by `LOCK TABLES`, with their lock mode (e.g. `READ`, `WRITE`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locks: Vec<(String, String)>,
+    // The deepest subquery/CTE nesting: 0 for a flat query
+    pub max_depth: usize,
+    // The stored procedures invoked with `CALL`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub procedures: Vec<String>,
+    // The literals the columns are compared with (`=`, `<>`, `<`, `IN`, `BETWEEN`, ...),
+    // as (column, value) pairs. The values are rendered as SQL, e.g. `-100`, `'x'`,
+    // `DATE '2021-01-01'` or `true`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub predicate_values: Vec<(String, String)>,
+    // The number of leaf predicates of the WHERE clauses (subqueries included),
+    // splitting on AND/OR: 3 for `WHERE a = 1 AND (b > 2 OR c < 3)`
+    pub predicate_count: usize,
+    // The sequences used with `nextval`, `currval` and `setval` (Postgres)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sequences: Vec<String>,
+    // The caveats of the extraction, see `Warning`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+    // See `blast_radius()`. It's not serialized, so a deserialized result has the
+    // default `FullTable` radius.
+    #[serde(skip, default)]
+    blast_radius: BlastRadius,
+    // Heuristic: true when a projection mixes bare columns and aggregates without
+    // a GROUP BY, e.g. `SELECT dept, COUNT(*) FROM employees`. Only the well-known
+    // aggregate functions are recognized.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub invalid_aggregation: bool,
+    // The data types declared by `CREATE TABLE`, by `table.column`, e.g. `VARCHAR(255)`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_types: BTreeMap<String, String>,
+    // The table aliases, with the table they refer to (`u` -> `users`), following
+    // the aliases of aliases
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub alias_map: BTreeMap<String, String>,
+    // The GROUP BY keys: the columns, alias-resolved like `columns`, and the other
+    // expressions rendered as SQL (e.g. `DATE_TRUNC('month', created_at)`).
+    // `GROUP BY ALL` groups by the projected expressions that are not aggregates.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group_by: Vec<String>,
+    // The names of the common table expressions (`WITH name AS (...)`),
+    // which are not listed in `tables`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ctes: Vec<String>,
+    // The CTEs reading other CTEs of the same `WITH`, as (cte, dependency) pairs:
+    // `("b", "a")` for `WITH a AS (...), b AS (SELECT * FROM a) ...`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cte_dependencies: Vec<(String, String)>,
+    // The ORDER BY keys, in order (not sorted, as the order is meaningful)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order_by: Vec<OrderByColumn>,
+    // The joins of the queries, with their type and the columns of their condition
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub joins: Vec<JoinInfo>,
+    // The `JSON_TABLE` (MySQL) and `OPENJSON` (MsSql) table functions, with the
+    // columns they define
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub json_tables: Vec<JsonTable>,
+    // The schema of the INSERT, UPDATE or DELETE target, when qualified:
+    // `tenant_42` for `INSERT INTO tenant_42.orders ...`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_schema: Option<String>,
+    // The columns of the updated table referenced by the subqueries of the
+    // UPDATE values: `t.id` for `UPDATE t SET n = (SELECT COUNT(*) FROM x WHERE x.t_id = t.id)`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub correlated_columns: Vec<String>,
+    // The functions called, uppercased (`COUNT`, `DATE_TRUNC`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<String>,
+    // The window functions (called with `OVER`), uppercased (`ROW_NUMBER`, `SUM`).
+    // They are listed in `functions` as well.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub window_functions: Vec<String>,
+    // The RETURNING items of an INSERT, UPDATE or DELETE, in order: the columns,
+    // `*`, or the other expressions rendered as SQL
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub returning: Vec<String>,
+    // The base tables written through a view targeted by an INSERT, UPDATE or
+    // DELETE, resolved with `InspectOptions::views`. Empty when the target is not a view.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolved_targets: Vec<String>,
+    // The columns of `columns` that can't be attributed to a table: the unqualified
+    // ones of the queries reading many tables, e.g. `address` in
+    // `SELECT address FROM t1 JOIN t2 ON t1.id = t2.id`. With a single table,
+    // they belong to it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unresolved_columns: Vec<String>,
+    // The schemas of the qualified tables: `public` for `public.orders`,
+    // and for `sales.public.orders` (where `sales` is the database)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schemas: Vec<String>,
+    // A sortable complexity score of the statement:
+    // `2 * joins + predicates + 3 * max_depth + 2 * subqueries + function calls`,
+    // where `joins` counts the entries of `joins`, `predicates` is `predicate_count`,
+    // `subqueries` counts the nested queries (CTEs included) and the function
+    // calls are counted one by one (`COUNT(a), COUNT(b)` is 2)
+    pub complexity_score: u32,
+    // True for `SELECT DISTINCT` and `SELECT DISTINCT ON (...)`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub distinct: bool,
+    // The `DISTINCT ON` (Postgres) expressions, in order: the columns, alias-resolved
+    // like `columns`, and the other expressions rendered as SQL
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub distinct_on: Vec<String>,
+    // The columns of the WHERE clauses (of the subqueries too), alias-resolved
+    // like `columns`, e.g. to suggest indexes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filter_columns: Vec<String>,
+    // The columns of the HAVING clauses, alias-resolved like `columns`, the ones
+    // inside the aggregate functions too (`orders.id` for `HAVING COUNT(o.id) > 5`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub having_columns: Vec<String>,
+    // The aliases of the derived tables (the subqueries in FROM), e.g. `sub` for
+    // `FROM (SELECT ...) sub`, which are not listed in `tables`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived_tables: Vec<String>,
+    // The kind of the dropped objects (`TABLE`, `VIEW`, `INDEX`, ...) of a `DROP`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_type: Option<String>,
+    // The INSERT ... SELECT columns, as (target column, source) pairs in the order of
+    // the INSERT columns. The source is the column, alias-resolved like `columns`,
+    // or the expression rendered as SQL. The pairs stop at a projected wildcard.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lineage: Vec<(String, String)>,
+    // The bind parameter placeholders (`$1`, `?`, `:name`), in order and with
+    // duplicates: `?, ?` are two parameters
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<String>,
 }
 
-impl fmt::Display for ExtractResult {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?} {:?}", self.tables, self.columns)
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+// Options to tune the extraction
+#[derive(Debug, Clone)]
+pub struct InspectOptions {
+    // Fill `source` with the statement's slice of the original input
+    pub include_source: bool,
+    // Fail with `InspectError::Unsupported` on statements the inspector doesn't handle,
+    // instead of returning an empty result
+    pub strict_unsupported: bool,
+    // The SQL dialect used to parse the queries
+    pub dialect: Dialect,
+    // How to fold the case of the unquoted identifiers
+    pub fold_case: Casing,
+    // The view definitions (view name -> defining query), used to resolve
+    // the tables a view depends on
+    pub views: HashMap<String, String>,
+    // Prefix the INSERT columns with the target table (`users.id`), on by default.
+    // When off, the bare names are recorded (`id`).
+    pub qualify_insert_columns: bool,
+    // The columns of the tables (table name -> column names), used to expand the wildcards
+    pub schema: HashMap<String, Vec<String>>,
+    // Replace the projected `*` with the columns of the tables, from `schema`
+    pub expand_wildcards: bool,
+    // What to do with a `*` that can't be expanded
+    pub wildcard_policy: WildcardPolicy,
+    // List only the base tables in `tables`, leaving out the table functions
+    // (`FROM generate_series(1, 10)`). The CTE names and the derived-table
+    // aliases are never listed.
+    pub base_tables_only: bool,
+    // Dedupe `tables` and `columns` ignoring the case (`users` and `USERS`), keeping
+    // the casing seen first. Unlike `fold_case`, the identifiers are not altered.
+    pub case_insensitive_dedup: bool,
+    // List `tables` and `columns` in query order (the order they are first seen,
+    // e.g. the projection order) instead of sorted
+    pub ordered: bool,
+    // Replace the table aliases with the tables in the qualified columns (`u.id` ->
+    // `users.id`), on by default. When off, the columns are kept as written;
+    // `alias_map` still lists the aliases.
+    pub resolve_aliases: bool,
+    // Keep the quotes of the quoted identifiers in the names (`"Users"`, `` `id` ``),
+    // so that they are told apart from the unquoted ones. By default the quotes
+    // are stripped: `"Users"` and `Users` are both `Users`.
+    pub preserve_quotes: bool,
+}
+
+// The handling of a `*` that can't be expanded, because a table (or a subquery)
+// is not in the schema
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum WildcardPolicy {
+    // Keep the `*` in the columns, with a `Warning::UnexpandedWildcard`
+    #[default]
+    KeepStar,
+    // Fail with `InspectError::UnexpandedWildcard`
+    Error,
+    // Remove the `*` from the columns
+    Drop,
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        InspectOptions {
+            include_source: false,
+            strict_unsupported: false,
+            dialect: Dialect::default(),
+            fold_case: Casing::default(),
+            views: HashMap::new(),
+            qualify_insert_columns: true,
+            schema: HashMap::new(),
+            expand_wildcards: false,
+            wildcard_policy: WildcardPolicy::default(),
+            base_tables_only: false,
+            case_insensitive_dedup: false,
+            ordered: false,
+            resolve_aliases: true,
+            preserve_quotes: false,
+        }
     }
 }
 
-#[derive(Default)]
-struct V {
-    columns: HashSet<String>,
-    tables: HashSet<String>,
-    aliases: HashMap<String, String>,
-    target_table: String, // This is the target table in the INSERT, UPDATE or DELETE statements case
-    query_type: QueryType,
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    #[default]
+    Generic,
+    Postgres,
+    MySql,
+    Sqlite,
+    MsSql,
+    Ansi,
+    Snowflake,
 }
 
-fn join(arr: &[Ident]) -> String {
-    let mut result = String::new();
-    for (index, s) in arr.iter().enumerate() {
-        if index > 0 {
-            result.push('.');
+impl Dialect {
+    fn parser_dialect(&self) -> Box<dyn sqlparser::dialect::Dialect> {
+        match self {
+            Dialect::Generic => Box::new(GenericDialect {}),
+            Dialect::Postgres => Box::new(PostgreSqlDialect {}),
+            Dialect::MySql => Box::new(MySqlDialect {}),
+            Dialect::Sqlite => Box::new(SQLiteDialect {}),
+            Dialect::MsSql => Box::new(MsSqlDialect {}),
+            Dialect::Ansi => Box::new(AnsiDialect {}),
+            Dialect::Snowflake => Box::new(SnowflakeDialect {}),
         }
-        let q = s.to_string();
-        result.push_str(q.as_str());
     }
-    result
 }
 
-#[allow(clippy::assigning_clones)]
-impl Visitor for V {
-    type Break = ();
+// Case folding of the unquoted identifiers. Quoted identifiers are always preserved.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Casing {
+    #[default]
+    Preserve,
+    Lower,
+    Upper,
+    // Applies the native rule of the dialect: Postgres folds to lowercase,
+    // ANSI and Snowflake to uppercase, the others (MySQL, SQLite, MsSql and Generic) preserve
+    // the identifiers, as their behavior depends on the server configuration.
+    Dialect,
+}
 
-    fn pre_visit_statement(&mut self, _stmt: &Statement) -> ControlFlow<Self::Break> {
-        match _stmt {
-            Statement::Query(q) => {
-                self.query_type = QueryType::SELECT;
-                if let SetExpr::Select(select) = (q.body).as_ref() {
-                    for select_item in &select.projection {
-                        if let SelectItem::UnnamedExpr(expr) = select_item {
-                            if let Expr::Identifier(ident) = expr {
-                                self.columns.insert(ident.value.clone());
-                            } else if let Expr::CompoundIdentifier(ident) = expr {
-                                // This is a compound identifier, like table.column
-                                let full_name = join(ident);
-                                self.columns.insert(full_name);
-                            }
-                        } else if let SelectItem::ExprWithAlias { expr, alias: _ } = select_item {
-                            if let Expr::Identifier(ident) = expr {
-                                self.columns.insert(ident.value.clone());
-                            } else if let Expr::CompoundIdentifier(ident) = expr {
-                                // This is a compound identifier, like table.column
-                                let full_name = join(ident);
-                                self.columns.insert(full_name);
-                            }
-                        } else if let SelectItem::Wildcard(_expr) = select_item {
-                            self.columns.insert("*".to_string());
-                        }
-                    }
-                }
+impl Casing {
+    fn resolve(self, dialect: Dialect) -> Casing {
+        match (self, dialect) {
+            (Casing::Dialect, Dialect::Postgres) => Casing::Lower,
+            (Casing::Dialect, Dialect::Ansi | Dialect::Snowflake) => Casing::Upper,
+            (Casing::Dialect, _) => Casing::Preserve,
+            (casing, _) => casing,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InspectError {
+    // The SQL can't be parsed, with the snippet of SQL where the parser failed
+    Parse { error: ParserError, snippet: String },
+    // The kind of statement (e.g. `CreateIndex`) is not supported
+    Unsupported(String),
+    // The input has more than one statement (the number of statements),
+    // see `inspect_single`
+    MultipleStatements(usize),
+    // A `*` can't be expanded, as the table is not in the schema,
+    // see `WildcardPolicy::Error`
+    UnexpandedWildcard(String),
+}
+
+impl fmt::Display for InspectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InspectError::Parse { error, .. } => write!(f, "{error}"),
+            InspectError::Unsupported(kind) => write!(f, "unsupported statement: {kind}"),
+            InspectError::MultipleStatements(count) => {
+                write!(f, "expected a single statement, found {count}")
             }
-            Statement::Insert(i) => {
-                self.query_type = QueryType::INSERT;
-                // The "insert" statement has a table as a target
-                let table_name = i.table.to_string();
-                self.tables.insert(table_name.clone());
-                self.target_table = table_name.clone();
-                for i in &i.columns {
-                    let full_name = format!("{table_name}.{i}");
-                    self.columns.insert(full_name);
-                }
+            InspectError::UnexpandedWildcard(table) => {
+                write!(f, "can't expand the wildcard, unknown table: {table}")
             }
-            Statement::Update {
-                table,
-                assignments,
-                from: _,
-                selection: _,
-                returning: _,
-                or: _,
-            } => {
-                self.query_type = QueryType::UPDATE;
-                // The "insert" statement has a table as a target
-                let table_name = table.to_string();
-                self.target_table = table_name.clone();
+        }
+    }
+}
+
+impl std::error::Error for InspectError {}
+
+// How many rows a statement can touch, from the smallest to the largest
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BlastRadius {
+    SingleRow,
+    Filtered,
+    #[default]
+    FullTable,
+    MultiTable,
+}
+
+// A join between two tables, `left_table` being the table the condition pairs
+// `right_table` with (the previous table when it names none or many)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JoinInfo {
+    pub left_table: String,
+    pub right_table: String,
+    pub join_type: JoinType,
+    // The columns of the ON condition (alias-resolved like `columns`), or the
+    // USING columns. Empty for CROSS and NATURAL joins.
+    pub columns: Vec<String>,
+}
+
+// The kind of a join, mirroring `sqlparser::ast::JoinOperator` with the
+// synonyms merged (e.g. `LEFT JOIN` and `LEFT OUTER JOIN`). The comma-separated
+// tables of a FROM are `CROSS` joins.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JoinType {
+    Inner,
+    Left,
+    Right,
+    Full,
+    Cross,
+    Semi,
+    Anti,
+    CrossApply,
+    OuterApply,
+    AsOf,
+}
+
+impl From<&JoinOperator> for JoinType {
+    fn from(operator: &JoinOperator) -> Self {
+        match operator {
+            JoinOperator::Join(_) | JoinOperator::Inner(_) | JoinOperator::StraightJoin(_) => {
+                JoinType::Inner
+            }
+            JoinOperator::Left(_) | JoinOperator::LeftOuter(_) => JoinType::Left,
+            JoinOperator::Right(_) | JoinOperator::RightOuter(_) => JoinType::Right,
+            JoinOperator::FullOuter(_) => JoinType::Full,
+            JoinOperator::CrossJoin => JoinType::Cross,
+            JoinOperator::Semi(_) | JoinOperator::LeftSemi(_) | JoinOperator::RightSemi(_) => {
+                JoinType::Semi
+            }
+            JoinOperator::Anti(_) | JoinOperator::LeftAnti(_) | JoinOperator::RightAnti(_) => {
+                JoinType::Anti
+            }
+            JoinOperator::CrossApply => JoinType::CrossApply,
+            JoinOperator::OuterApply => JoinType::OuterApply,
+            JoinOperator::AsOf { .. } => JoinType::AsOf,
+        }
+    }
+}
+
+// A `JSON_TABLE` (MySQL) or `OPENJSON` (MsSql) table function
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct JsonTable {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    // The JSON document: the column, alias-resolved like `columns`,
+    // or the expression rendered as SQL
+    pub source: String,
+    // The columns defined by the function, in order (the nested ones included)
+    pub columns: Vec<String>,
+}
+
+// An ORDER BY key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderByColumn {
+    // The column, alias-resolved like `columns`, the other expressions rendered
+    // as SQL, or the position in the projection (`1`)
+    pub column: String,
+    // `Some(true)` for ASC, `Some(false)` for DESC, `None` when not specified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asc: Option<bool>,
+    // True when `column` is a position in the projection (`ORDER BY 1`)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub ordinal: bool,
+}
+
+// The caveats of an extraction, the result may be incomplete or imprecise
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "detail")]
+pub enum Warning {
+    // An unqualified column of a query reading many tables: its table can't be
+    // resolved without the schema
+    AmbiguousColumn(String),
+    // A GROUP BY/ORDER BY position that doesn't match a projected column
+    UnresolvedPosition(u64),
+    // A view that depends on itself, through its definition
+    CircularView(String),
+    // A deprecated construct, e.g. `VALUES(col)` in MySQL `ON DUPLICATE KEY UPDATE`
+    DeprecatedSyntax(String),
+    // A `*` kept in the columns, as the table is not in the schema
+    UnexpandedWildcard(String),
+}
+
+impl fmt::Display for ExtractResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} {:?}", self.tables, self.columns)
+    }
+}
+
+impl ExtractResult {
+    // Renders the result as a single SQL comment, e.g.:
+    // `/* sql-inspector: tables=users; columns=id,name; type=SELECT */`
+    // Comment delimiters in the identifiers are broken up, so the comment
+    // can't be closed (or nested) early.
+    pub fn to_sql_comment(&self) -> String {
+        let escape = |names: &[String]| {
+            names
+                .iter()
+                .map(|n| n.replace("*/", "* /").replace("/*", "/ *"))
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        format!(
+            "/* sql-inspector: tables={}; columns={}; type={:?} */",
+            escape(&self.tables),
+            escape(&self.columns),
+            self.query_type
+        )
+    }
+
+    // Heuristic classification of how many rows the statement can touch, for
+    // change-risk scoring. It's based on the WHERE clause of the main statement
+    // (the SELECT source for INSERT ... SELECT) and the number of tables:
+    // - `SingleRow`: the WHERE has a primary-key-looking equality (a column named
+    //   `id` compared with a literal or a placeholder) in its top-level ANDs, or
+    //   the INSERT has a single row of values
+    // - `Filtered`: any other WHERE, or many rows of values
+    // - `FullTable`: no WHERE, on a single table
+    // - `MultiTable`: no WHERE, on many tables (subqueries included)
+    // The schema is unknown, so the real keys and the join cardinalities are not
+    // taken into account. The radius is not serialized: it doesn't survive a
+    // serialization round-trip, the deserialized result has the default `FullTable`.
+    pub fn blast_radius(&self) -> BlastRadius {
+        self.blast_radius
+    }
+
+    // Returns the column names without the table prefix, deduped and sorted
+    pub fn bare_columns(&self) -> Vec<String> {
+        let mut columns: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| c.rsplit('.').next().unwrap_or(c).to_string())
+            .collect();
+        columns.sort();
+        columns.dedup();
+        columns
+    }
+
+    // A low-cardinality label for metrics: the lowercased query type and the
+    // number of tables, bucketed to 0, 1, 2 or 3+ (e.g. `select:2tables`)
+    pub fn metric_label(&self) -> String {
+        let query_type = format!("{:?}", self.query_type).to_lowercase();
+        match self.tables.len() {
+            1 => format!("{query_type}:1table"),
+            count @ (0 | 2) => format!("{query_type}:{count}tables"),
+            _ => format!("{query_type}:3+tables"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct V {
+    // Insertion-ordered, for `InspectOptions::ordered`
+    columns: IndexSet<String>,
+    tables: IndexSet<String>,
+    aliases: HashMap<String, String>,
+    target_table: String, // This is the target table in the INSERT, UPDATE or DELETE statements case
+    query_type: QueryType,
+    locking: Option<String>,
+    locked_tables: HashSet<String>,
+    databases: HashSet<String>,
+    materialized: bool,
+    limit: Option<u64>,
+    offset: Option<u64>,
+    projection: Vec<String>, // The projected columns, in order and with duplicates
+    unsupported: Option<String>, // The kind of the first statement not handled by the visitor
+    select_into: bool,       // A `SELECT ... INTO` creates a table
+    statement_depth: usize,  // The nesting of the statements, e.g. the SELECT of an EXPLAIN
+    overwrite: bool,
+    idempotent: bool,
+    locks: Vec<(String, String)>,
+    scope_depth: usize, // The number of queries we are nested in
+    max_depth: usize,
+    joins: Vec<(String, String)>, // The joined tables, by alias when they have one
+    procedures: HashSet<String>,
+    predicate_values: HashSet<(String, String)>,
+    predicate_count: usize,
+    sequences: HashSet<String>,
+    warnings: HashSet<Warning>,
+    blast_radius: Option<BlastRadius>, // Set when the statement is filtered (or inserts values)
+    bare_insert_columns: bool,         // See `InspectOptions::qualify_insert_columns`
+    invalid_aggregation: bool,
+    output_references: HashSet<Span>, // The ORDER BY references to the output column aliases
+    column_types: BTreeMap<String, String>,
+    wildcard_sources: Vec<Vec<String>>, // The FROM tables of each projected `*`
+    group_by: Vec<Expr>,
+    ctes: HashSet<String>,
+    cte_dependencies: HashSet<(String, String)>,
+    table_functions: HashSet<String>, // e.g. `generate_series` in `FROM generate_series(1, 10)`
+    base_tables_only: bool,           // See `InspectOptions::base_tables_only`
+    case_insensitive_dedup: bool,     // See `InspectOptions::case_insensitive_dedup`
+    ordered: bool,                    // See `InspectOptions::ordered`
+    keep_aliases: bool,               // See `InspectOptions::resolve_aliases`
+    seen: Vec<String>, // The table and column names in visit order, for `case_insensitive_dedup`
+    order_by: Vec<(Expr, Option<bool>)>, // The ORDER BY expressions, with their direction
+    join_infos: Vec<JoinInfo>, // By alias, resolved by `extract_result`
+    json_tables: Vec<JsonTable>, // The source is resolved by `extract_result`
+    target_schema: Option<String>,
+    subquery_columns: HashMap<Span, String>, // Unqualified columns, with the table they belong to
+    correlated_columns: HashSet<String>,
+    functions: HashSet<String>,
+    returning: Vec<String>,
+    target_name: Option<String>, // The INSERT, UPDATE or DELETE target, without alias
+    resolved_targets: Vec<String>,
+    schemas: HashSet<String>,
+    subquery_count: usize,
+    function_calls: usize,
+    distinct: bool,
+    distinct_on: Vec<Expr>,
+    has_limit: bool,
+    filter_columns: HashSet<String>,
+    lineage: Vec<(String, String)>,
+    object_type: Option<String>,
+    parameters: Vec<String>,
+    has_upsert: bool,
+    having_columns: HashSet<String>,
+    derived_tables: HashSet<String>,
+    window_functions: HashSet<String>,
+    database_qualified: bool, // The `db.table` names are qualified by a database (MySQL)
+    unresolved: HashSet<String>, // The unqualified columns of the scopes with many tables
+}
+
+fn join(arr: &[Ident]) -> String {
+    let mut result = String::new();
+    for (index, s) in arr.iter().enumerate() {
+        if index > 0 {
+            result.push('.');
+        }
+        // The quotes are not part of the name
+        result.push_str(s.value.as_str());
+    }
+    result
+}
+
+// The name of a (possibly qualified) object, without the quotes, e.g. `db.users`
+fn object_name(name: &ObjectName) -> String {
+    name.0
+        .iter()
+        .filter_map(|part| match part {
+            ObjectNamePart::Identifier(ident) => Some(ident.value.as_str()),
+            ObjectNamePart::Function(_) => None,
+        })
+        .collect::<Vec<&str>>()
+        .join(".")
+}
+
+// Rewrites the unquoted identifiers of the statements in the case given by the options,
+// so that the visitor doesn't have to care about folding. With `quotes`, the quotes
+// of the quoted identifiers are moved into their value instead (`"Users"` is then
+// an unquoted identifier named `"Users"`), so they are kept by every rendering.
+struct CaseFolder {
+    casing: Casing,
+    quotes: bool,
+}
+
+impl CaseFolder {
+    fn fold(&self, ident: &mut Ident) {
+        if let Some(quote) = ident.quote_style {
+            if self.quotes {
+                let close = if quote == '[' { ']' } else { quote };
+                ident.value = format!("{quote}{}{close}", ident.value);
+                ident.quote_style = None;
+            }
+            return;
+        }
+        match self.casing {
+            Casing::Lower => ident.value = ident.value.to_lowercase(),
+            Casing::Upper => ident.value = ident.value.to_uppercase(),
+            Casing::Preserve | Casing::Dialect => {}
+        }
+    }
+
+    fn fold_name(&self, name: &mut ObjectName) {
+        for part in name.0.iter_mut() {
+            if let ObjectNamePart::Identifier(ident) = part {
+                self.fold(ident);
+            }
+        }
+    }
+}
+
+impl VisitorMut for CaseFolder {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, statement: &mut Statement) -> ControlFlow<Self::Break> {
+        // The identifiers that are neither expressions nor relations
+        match statement {
+            Statement::Insert(insert) => {
+                insert.columns.iter_mut().for_each(|c| self.fold(c));
+                let assignments = match &mut insert.on {
+                    Some(OnInsert::OnConflict(on_conflict)) => {
+                        if let Some(ConflictTarget::Columns(columns)) =
+                            &mut on_conflict.conflict_target
+                        {
+                            columns.iter_mut().for_each(|c| self.fold(c));
+                        }
+                        match &mut on_conflict.action {
+                            OnConflictAction::DoUpdate(update) => Some(&mut update.assignments),
+                            OnConflictAction::DoNothing => None,
+                        }
+                    }
+                    Some(OnInsert::DuplicateKeyUpdate(assignments)) => Some(assignments),
+                    _ => None,
+                };
+                for assignment in assignments.into_iter().flatten() {
+                    if let AssignmentTarget::ColumnName(name) = &mut assignment.target {
+                        self.fold_name(name);
+                    }
+                }
+            }
+            Statement::Update { assignments, .. } => {
                 for assignment in assignments {
-                    let value = assignment.value.clone();
-                    let target = assignment.target.clone();
-                    match value {
-                        Expr::CompoundIdentifier(ident) => {
-                            // This is a compound identifier, like table.column
-                            let first = ident.first().unwrap();
-                            let second = ident.last().unwrap();
-                            let full_name = format!("{first}.{second}");
-                            self.columns.insert(full_name);
+                    if let AssignmentTarget::ColumnName(name) = &mut assignment.target {
+                        self.fold_name(name);
+                    }
+                }
+            }
+            Statement::CreateView { name, .. } => self.fold_name(name),
+            Statement::CreateTrigger { table_name, .. } => self.fold_name(table_name),
+            Statement::CreateTable(create) => create
+                .columns
+                .iter_mut()
+                .for_each(|c| self.fold(&mut c.name)),
+            Statement::AlterTable { operations, .. } => {
+                for operation in operations {
+                    match operation {
+                        AlterTableOperation::AddColumn { column_def, .. } => {
+                            self.fold(&mut column_def.name)
                         }
-                        Expr::Identifier(ident) => {
-                            let full_name = format!("{table_name}.{ident}");
-                            self.columns.insert(full_name);
+                        AlterTableOperation::DropColumn { column_names, .. } => {
+                            column_names.iter_mut().for_each(|c| self.fold(c))
+                        }
+                        AlterTableOperation::RenameColumn {
+                            old_column_name,
+                            new_column_name,
+                        } => {
+                            self.fold(old_column_name);
+                            self.fold(new_column_name);
+                        }
+                        AlterTableOperation::ChangeColumn {
+                            old_name, new_name, ..
+                        } => {
+                            self.fold(old_name);
+                            self.fold(new_name);
+                        }
+                        AlterTableOperation::ModifyColumn { col_name, .. } => self.fold(col_name),
+                        AlterTableOperation::AlterColumn { column_name, .. } => {
+                            self.fold(column_name)
+                        }
+                        AlterTableOperation::RenameTable { table_name } => {
+                            self.fold_name(table_name)
                         }
                         _ => {}
                     }
-                    if let AssignmentTarget::ColumnName(ident) = target {
-                        // It's a tuple with one vector of idents
-                        if (ident.0).len() == 1 {
-                            let column = ident.0.first().unwrap();
-                            let full_name = format!("{table_name}.{column}");
-                            self.columns.insert(full_name);
-                        } else {
-                            // Convert ObjectNameParts to Idents for join function
-                            let idents: Vec<Ident> = ident
-                                .0
-                                .iter()
-                                .filter_map(|part| match part {
-                                    ObjectNamePart::Identifier(ident) => Some(ident.clone()),
-                                    ObjectNamePart::Function(_) => None,
-                                })
-                                .collect();
-                            let full_name = join(&idents);
-                            self.columns.insert(full_name);
+                }
+            }
+            Statement::Drop { names, table, .. } => {
+                names.iter_mut().for_each(|n| self.fold_name(n));
+                table.iter_mut().for_each(|n| self.fold_name(n));
+            }
+            Statement::Merge { clauses, .. } => {
+                for clause in clauses {
+                    match &mut clause.action {
+                        MergeAction::Insert(insert) => {
+                            insert.columns.iter_mut().for_each(|c| self.fold(c))
                         }
+                        MergeAction::Update { assignments } => {
+                            for assignment in assignments {
+                                if let AssignmentTarget::ColumnName(name) = &mut assignment.target {
+                                    self.fold_name(name);
+                                }
+                            }
+                        }
+                        MergeAction::Delete => {}
                     }
                 }
-                self.tables.insert(table.to_string());
             }
-            Statement::Delete(delete) => {
-                self.query_type = QueryType::DELETE;
-                if let FromTable::WithFromKeyword(tables) = &delete.from {
-                    self.target_table = tables[0].to_string();
-                    // In mysql, the FROM clause can have multiple tables
-                    for i in tables {
-                        self.tables.insert(i.to_string());
+            Statement::LockTables { tables } => {
+                for lock in tables {
+                    self.fold(&mut lock.table);
+                    if let Some(alias) = &mut lock.alias {
+                        self.fold(alias);
                     }
                 }
             }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<Self::Break> {
+        self.fold_name(relation);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        if let TableFactor::Table {
+            alias: Some(alias), ..
+        }
+        | TableFactor::Derived {
+            alias: Some(alias), ..
+        } = table_factor
+        {
+            self.fold(&mut alias.name);
+        }
+        ControlFlow::Continue(())
+    }
 
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            Expr::Identifier(ident) => self.fold(ident),
+            Expr::CompoundIdentifier(idents) => idents.iter_mut().for_each(|i| self.fold(i)),
             _ => {}
         }
         ControlFlow::Continue(())
     }
+}
 
-    fn pre_visit_table_factor(&mut self, _table_factor: &TableFactor) -> ControlFlow<Self::Break> {
-        // Here we extract aliases for table names
-        if let TableFactor::Table { name, alias, .. } = _table_factor {
-            let table_name = name.to_string();
-            self.tables.insert(table_name.clone());
-            if let Some(alias) = alias {
-                let alias = alias.to_string();
-                self.aliases.insert(alias, table_name);
+fn fold_case(statements: &mut Vec<Statement>, options: &InspectOptions) {
+    let casing = options.fold_case.resolve(options.dialect);
+    if casing != Casing::Preserve {
+        let _ = statements.visit(&mut CaseFolder {
+            casing,
+            quotes: false,
+        });
+    }
+    // In a second pass, as the identifiers visited twice must not be quoted twice
+    if options.preserve_quotes {
+        let _ = statements.visit(&mut CaseFolder {
+            casing: Casing::Preserve,
+            quotes: true,
+        });
+    }
+}
+
+// Returns the name of the statement variant, e.g. `CreateTable`
+fn statement_kind(statement: &Statement) -> String {
+    let debug = format!("{statement:?}");
+    debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+// Pairs the INSERT columns with the projected items of the SELECT, in order: the
+// source columns (the unqualified ones qualified with the table when the SELECT
+// reads one), or the other expressions rendered as SQL. The positions after a
+// wildcard are unknown, so the pairing stops there.
+fn insert_lineage(targets: impl Iterator<Item = String>, select: &Select) -> Vec<(String, String)> {
+    let table = match select.from.as_slice() {
+        [from] if from.joins.is_empty() => table_factor_reference(&from.relation),
+        _ => None,
+    };
+    targets
+        .zip(&select.projection)
+        .map_while(|(target, item)| {
+            let expr = match item {
+                SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => expr,
+                _ => return None,
+            };
+            let source = match (expr, &table) {
+                (Expr::Identifier(ident), Some(table)) => format!("{table}.{}", ident.value),
+                _ => column_name(expr).unwrap_or_else(|| expr.to_string()),
+            };
+            Some((target, source))
+        })
+        .collect()
+}
+
+// The tables a `*` projects: the FROM tables, or the alias (or the SQL) of
+// the other sources, e.g. subqueries, which are not in the schema
+fn select_sources(select: &Select) -> Vec<String> {
+    select
+        .from
+        .iter()
+        .flat_map(|from| {
+            std::iter::once(&from.relation).chain(from.joins.iter().map(|j| &j.relation))
+        })
+        .map(|table_factor| match table_factor {
+            TableFactor::Table { name, .. } => object_name(name),
+            TableFactor::Derived {
+                alias: Some(alias), ..
+            } => alias.name.value.clone(),
+            _ => table_factor.to_string(),
+        })
+        .collect()
+}
+
+// The schema of a qualified table name, e.g. `tenant_42` in `tenant_42.orders`
+fn table_schema(name: &ObjectName) -> Option<String> {
+    match name.0.as_slice() {
+        [.., schema, _] => schema.as_ident().map(|ident| ident.value.clone()),
+        _ => None,
+    }
+}
+
+// The schema of a qualified table, see `table_schema`
+fn table_factor_schema(table_factor: &TableFactor) -> Option<String> {
+    match table_factor {
+        TableFactor::Table { name, .. } => table_schema(name),
+        _ => None,
+    }
+}
+
+// The items of a RETURNING clause: the columns, `*`, or the expressions rendered as SQL
+fn returning_items(items: &[SelectItem]) -> Vec<String> {
+    items
+        .iter()
+        .map(|item| match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                column_name(expr).unwrap_or_else(|| expr.to_string())
             }
+            SelectItem::Wildcard(_) => "*".to_string(),
+            SelectItem::QualifiedWildcard(kind, _) => format!("{kind}.*"),
+        })
+        .collect()
+}
+
+// The name of a table, e.g. `users` (but not its alias)
+fn table_factor_name(table_factor: &TableFactor) -> Option<String> {
+    match table_factor {
+        TableFactor::Table { name, .. } => Some(object_name(name)),
+        _ => None,
+    }
+}
+
+// The name a table is referenced by in the query: its alias or its name
+fn table_factor_reference(table_factor: &TableFactor) -> Option<String> {
+    match table_factor {
+        TableFactor::Table {
+            alias: Some(alias), ..
+        } => Some(alias.name.value.clone()),
+        TableFactor::Table { name, .. } => Some(object_name(name)),
+        _ => None,
+    }
+}
+
+// The table a join pairs the joined table with: the one its condition names,
+// otherwise the previous table
+fn join_left_table(qualifiers: &HashSet<String>, previous: &Option<String>) -> Option<String> {
+    match qualifiers.iter().collect::<Vec<&String>>().as_slice() {
+        [table] => Some(table.to_string()),
+        _ => previous.clone(),
+    }
+}
+
+// The columns of a join condition: the ON columns or the USING ones
+fn join_columns(operator: &JoinOperator) -> Vec<String> {
+    let mut columns = vec![];
+    let _ = visit_expressions(operator, |expr| {
+        columns.extend(column_name(expr));
+        ControlFlow::<()>::Continue(())
+    });
+    let constraint = match operator {
+        JoinOperator::Join(c)
+        | JoinOperator::Inner(c)
+        | JoinOperator::Left(c)
+        | JoinOperator::LeftOuter(c)
+        | JoinOperator::Right(c)
+        | JoinOperator::RightOuter(c)
+        | JoinOperator::FullOuter(c)
+        | JoinOperator::Semi(c)
+        | JoinOperator::LeftSemi(c)
+        | JoinOperator::RightSemi(c)
+        | JoinOperator::Anti(c)
+        | JoinOperator::LeftAnti(c)
+        | JoinOperator::RightAnti(c)
+        | JoinOperator::StraightJoin(c)
+        | JoinOperator::AsOf { constraint: c, .. } => Some(c),
+        JoinOperator::CrossJoin | JoinOperator::CrossApply | JoinOperator::OuterApply => None,
+    };
+    if let Some(JoinConstraint::Using(names)) = constraint {
+        columns.extend(names.iter().map(object_name));
+    }
+    columns
+}
+
+// The columns defined by a `JSON_TABLE`, including the `NESTED PATH` ones
+fn json_table_columns(columns: &[JsonTableColumn]) -> Vec<String> {
+    columns
+        .iter()
+        .flat_map(|column| match column {
+            JsonTableColumn::Named(named) => vec![named.name.value.clone()],
+            JsonTableColumn::ForOrdinality(name) => vec![name.value.clone()],
+            JsonTableColumn::Nested(nested) => json_table_columns(&nested.columns),
+        })
+        .collect()
+}
+
+// The name of a column expression, e.g. `name` or `u.name`
+fn column_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Identifier(ident) => Some(ident.value.clone()),
+        Expr::CompoundIdentifier(idents) => Some(
+            idents
+                .iter()
+                .map(|i| i.value.as_str())
+                .collect::<Vec<&str>>()
+                .join("."),
+        ),
+        _ => None,
+    }
+}
+
+// Renders a literal as SQL, including the negative numbers (which are parsed
+// as a unary minus applied to the number) and the typed literals (`DATE '...'`).
+// Placeholders are not literals.
+fn literal_string(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Value(ValueWithSpan {
+            value: Value::Placeholder(_),
+            ..
+        }) => None,
+        Expr::Value(value) => Some(value.to_string()),
+        Expr::TypedString { .. } => Some(expr.to_string()),
+        Expr::UnaryOp {
+            op: UnaryOperator::Minus | UnaryOperator::Plus,
+            expr: inner,
+        } => literal_string(inner).map(|_| expr.to_string()),
+        Expr::Nested(inner) => literal_string(inner),
+        _ => None,
+    }
+}
+
+// A filter on a column named `id`, e.g. `id = 1` or `u.id = $1`
+fn is_key_equality(expr: &Expr) -> bool {
+    match expr {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => is_key_equality(left) || is_key_equality(right),
+        Expr::Nested(inner) => is_key_equality(inner),
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::Eq,
+            right,
+        } => [(left, right), (right, left)]
+            .iter()
+            .any(|(column, value)| {
+                let is_id = column_name(column).is_some_and(|c| {
+                    c.rsplit('.')
+                        .next()
+                        .unwrap_or(&c)
+                        .eq_ignore_ascii_case("id")
+                });
+                let is_value = literal_string(value).is_some()
+                    || matches!(
+                        value.as_ref(),
+                        Expr::Value(ValueWithSpan {
+                            value: Value::Placeholder(_),
+                            ..
+                        })
+                    );
+                is_id && is_value
+            }),
+        _ => false,
+    }
+}
+
+fn filter_radius(selection: Option<&Expr>) -> Option<BlastRadius> {
+    selection.map(|selection| match is_key_equality(selection) {
+        true => BlastRadius::SingleRow,
+        false => BlastRadius::Filtered,
+    })
+}
+
+fn query_radius(query: &Query) -> Option<BlastRadius> {
+    match query.body.as_ref() {
+        SetExpr::Select(select) => filter_radius(select.selection.as_ref()),
+        SetExpr::Values(values) if values.rows.len() == 1 => Some(BlastRadius::SingleRow),
+        SetExpr::Values(_) => Some(BlastRadius::Filtered),
+        _ => None,
+    }
+}
+
+// True when the expression calls an aggregate function, e.g. `COUNT(*)`.
+// Window functions (`SUM(x) OVER (...)`) are not aggregates.
+fn has_aggregate(expr: &Expr) -> bool {
+    const AGGREGATES: [&str; 14] = [
+        "COUNT",
+        "SUM",
+        "AVG",
+        "MIN",
+        "MAX",
+        "ARRAY_AGG",
+        "STRING_AGG",
+        "GROUP_CONCAT",
+        "JSON_AGG",
+        "BOOL_AND",
+        "BOOL_OR",
+        "EVERY",
+        // The ordered-set aggregates, with a `WITHIN GROUP (ORDER BY ...)`
+        "PERCENTILE_CONT",
+        "PERCENTILE_DISC",
+    ];
+    visit_expressions(expr, |expr| match expr {
+        Expr::Function(f)
+            if f.over.is_none()
+                && AGGREGATES
+                    .iter()
+                    .any(|a| f.name.to_string().eq_ignore_ascii_case(a)) =>
+        {
+            ControlFlow::Break(())
         }
+        _ => ControlFlow::Continue(()),
+    })
+    .is_break()
+}
+
+// Counts the leaf predicates of a condition, splitting on AND/OR
+fn count_predicates(expr: &Expr) -> usize {
+    match expr {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And | BinaryOperator::Or,
+            right,
+        } => count_predicates(left) + count_predicates(right),
+        Expr::Nested(inner)
+        | Expr::UnaryOp {
+            op: UnaryOperator::Not,
+            expr: inner,
+        } => count_predicates(inner),
+        _ => 1,
+    }
+}
+
+// Returns the value of a numeric literal, like the `10` in `LIMIT 10`
+fn literal_u64(expr: &Expr) -> Option<u64> {
+    match expr {
+        Expr::Value(ValueWithSpan {
+            value: Value::Number(n, _),
+            ..
+        }) => n.parse().ok(),
+        _ => None,
+    }
+}
+
+// Collects the unqualified columns of a query scope, without the nested queries
+#[derive(Default)]
+struct ScopeColumns {
+    depth: usize,
+    columns: HashSet<String>,
+    spans: HashSet<Span>,
+    qualified: HashSet<String>, // The qualified columns (`u.id`) of the scope
+}
+
+impl Visitor for ScopeColumns {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        self.depth += 1;
         ControlFlow::Continue(())
     }
 
-    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<Self::Break> {
-        // Relation === table name
-        if let Some(ObjectNamePart::Identifier(ident)) = relation.0.first() {
-            let table_name = ident.value.clone();
-            self.tables.insert(table_name);
-        }
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        self.depth -= 1;
         ControlFlow::Continue(())
     }
 
     fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
-        if let Expr::Wildcard(_) = expr {
-            self.columns.insert("*".to_string());
-        }
         if let Expr::Identifier(ident) = expr {
-            self.columns.insert(ident.value.clone());
+            if self.depth == 0 {
+                self.columns.insert(ident.value.clone());
+                self.spans.insert(ident.span);
+            }
         }
-
-        if let Expr::CompoundIdentifier(idents) = expr {
-            let mut full_column = String::new();
-            for ident in idents {
-                full_column.push_str(&ident.value);
-                full_column.push('.');
+        if let Expr::CompoundIdentifier(_) = expr {
+            if self.depth == 0 {
+                self.qualified.extend(column_name(expr));
             }
-            full_column.pop();
-            self.columns.insert(full_column);
         }
-
         ControlFlow::Continue(())
     }
 }
 
-fn inspect(sql: &str) -> ExtractResult {
-    let statements = Parser::parse_sql(&GenericDialect {}, sql).unwrap();
-    let mut visitor = V::default();
-    let _ = statements.visit(&mut visitor);
-    let mut columns: Vec<String> = Vec::from_iter(visitor.columns.iter().map(|c| c.to_string()));
-    // We replace the aliases with the real table name for
-    // the fully-qualified columns
-    for c in columns.iter_mut() {
-        if !c.contains('.') {
-            continue;
+impl V {
+    // Records the casing of a table or column name, when deduping case-insensitively
+    fn see(&mut self, name: String) {
+        if self.case_insensitive_dedup {
+            self.seen.push(name);
         }
-        let prefix = c.split('.').next().unwrap();
-        let col = c.split('.').next_back().unwrap();
-        if let Some(alias) = visitor.aliases.get(prefix) {
-            *c = format!("{}.{}", alias, col);
+    }
+
+    fn with_options(options: &InspectOptions) -> V {
+        V {
+            bare_insert_columns: !options.qualify_insert_columns,
+            base_tables_only: options.base_tables_only,
+            case_insensitive_dedup: options.case_insensitive_dedup,
+            ordered: options.ordered,
+            keep_aliases: !options.resolve_aliases,
+            database_qualified: options.dialect == Dialect::MySql,
+            ..Default::default()
         }
     }
 
-    let mut tables: Vec<String> = Vec::from_iter(visitor.tables.iter().map(|c| c.to_string()));
-    columns.sort();
-    tables.sort();
-    let target_table = visitor.target_table.clone();
-    let query_type = visitor.query_type;
-    ExtractResult {
-        columns,
-        tables,
-        target_table,
-        query_type,
+    // An empty visitor with the same options, for a nested statement
+    fn nested(&self) -> V {
+        V {
+            bare_insert_columns: self.bare_insert_columns,
+            base_tables_only: self.base_tables_only,
+            case_insensitive_dedup: self.case_insensitive_dedup,
+            ordered: self.ordered,
+            keep_aliases: self.keep_aliases,
+            database_qualified: self.database_qualified,
+            ..Default::default()
+        }
     }
-}
 
-// This is the entry point for the WASM module, return the result as a JS object
-#[wasm_bindgen(skip_typescript)]
-pub fn sqlinspector(sql: &str) -> JsValue {
-    let res = inspect(sql);
-    serde_wasm_bindgen::to_value(&res).unwrap()
-}
+    // Collects the columns of a WHERE clause, without the nested queries
+    // (their own WHERE is visited with them)
+    fn visit_filter(&mut self, selection: &Expr) {
+        let mut scope = ScopeColumns::default();
+        let _ = selection.visit(&mut scope);
+        self.filter_columns.extend(scope.columns);
+        self.filter_columns.extend(scope.qualified);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // With many tables, the unqualified columns of the scope can't be resolved
+    fn visit_ambiguous<T: Visit>(&mut self, scope_node: &T) {
+        let mut scope = ScopeColumns::default();
+        let _ = scope_node.visit(&mut scope);
+        for column in scope.columns {
+            self.unresolved.insert(column.clone());
+            self.warnings.insert(Warning::AmbiguousColumn(column));
+        }
+    }
 
-    fn test_extract(sql: &str, columns: Vec<&str>, tables: Vec<&str>, query_type: QueryType) {
-        let res = inspect(sql);
-        assert_eq!(res.columns, columns);
-        assert_eq!(res.tables, tables);
-        assert_eq!(res.query_type, query_type);
+    // Collects the columns of a HAVING clause, the aggregated ones too (`COUNT(o.id) > 5`)
+    fn visit_having(&mut self, having: &Expr) {
+        let mut scope = ScopeColumns::default();
+        let _ = having.visit(&mut scope);
+        self.having_columns.extend(scope.columns);
+        self.having_columns.extend(scope.qualified);
     }
 
-    #[test]
-    fn select() {
-        let tests = vec![
-            (
-                // simple
-                "SELECT id FROM users WHERE age > 30",
+    // Collects the (column, literal) pairs of a comparison
+    fn visit_predicate(&mut self, expr: &Expr) {
+        let pairs: Vec<(&Expr, &Expr)> = match expr {
+            Expr::BinaryOp {
+                left,
+                op:
+                    BinaryOperator::Eq
+                    | BinaryOperator::NotEq
+                    | BinaryOperator::Lt
+                    | BinaryOperator::LtEq
+                    | BinaryOperator::Gt
+                    | BinaryOperator::GtEq,
+                right,
+            } => vec![(left, right), (right, left)],
+            Expr::InList { expr, list, .. } => list.iter().map(|value| (&**expr, value)).collect(),
+            Expr::Between {
+                expr, low, high, ..
+            } => vec![(expr, low), (expr, high)],
+            _ => vec![],
+        };
+        for (column, value) in pairs {
+            if let (Some(column), Some(value)) = (column_name(column), literal_string(value)) {
+                self.predicate_values.insert((column, value));
+            }
+        }
+    }
+
+    // Collects the tables of `TABLE name` queries, e.g. `INSERT INTO target TABLE source`.
+    // These are not relations, so `pre_visit_relation` doesn't see them.
+    fn visit_table_expr(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Table(table) => {
+                if let Some(table_name) = &table.table_name {
+                    let full_name = match &table.schema_name {
+                        Some(schema_name) => format!("{schema_name}.{table_name}"),
+                        None => table_name.clone(),
+                    };
+                    self.tables.insert(full_name);
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.visit_table_expr(left);
+                self.visit_table_expr(right);
+            }
+            _ => {}
+        }
+    }
+
+    // Collects the pairs of joined tables and counts the predicates of the queries
+    fn visit_selects(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for from in &select.from {
+                    self.visit_table_with_joins(from);
+                }
+                // `FROM a, b` is a cross join of `b` with the last table before it
+                for pair in select.from.windows(2) {
+                    let last = pair[0]
+                        .joins
+                        .last()
+                        .map_or(&pair[0].relation, |j| &j.relation);
+                    if let (Some(left), Some(right)) = (
+                        table_factor_reference(last),
+                        table_factor_reference(&pair[1].relation),
+                    ) {
+                        self.join_infos.push(JoinInfo {
+                            left_table: left,
+                            right_table: right,
+                            join_type: JoinType::Cross,
+                            columns: vec![],
+                        });
+                    }
+                }
+                if let Some(selection) = &select.selection {
+                    self.predicate_count += count_predicates(selection);
+                    self.visit_filter(selection);
+                }
+                if let Some(having) = &select.having {
+                    self.visit_having(having);
+                }
+                self.visit_aggregation(select);
+                self.visit_group_by(select);
+                // `u.*` is not an expression, so `pre_visit_expr` doesn't see it in the
+                // subqueries and in the source of `INSERT ... SELECT`
+                for item in &select.projection {
+                    if let SelectItem::QualifiedWildcard(
+                        SelectItemQualifiedWildcardKind::ObjectName(name),
+                        _,
+                    ) = item
+                    {
+                        self.columns.insert(format!("{}.*", object_name(name)));
+                    }
+                }
+                let table_count: usize = select.from.iter().map(|f| 1 + f.joins.len()).sum();
+                if table_count > 1 {
+                    self.visit_ambiguous(select.as_ref());
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.visit_selects(left);
+                self.visit_selects(right);
+            }
+            _ => {}
+        }
+    }
+
+    // A joined table is paired with the tables its ON condition refers to, or with
+    // the previous table when the condition doesn't name any (USING, NATURAL, CROSS JOIN)
+    fn visit_table_with_joins(&mut self, from: &TableWithJoins) {
+        let mut previous = table_factor_reference(&from.relation);
+        for join in &from.joins {
+            let joined = table_factor_reference(&join.relation);
+            if let Some(joined) = &joined {
+                let mut qualifiers = HashSet::new();
+                let _ = visit_expressions(&join.join_operator, |expr| {
+                    if let Expr::CompoundIdentifier(idents) = expr {
+                        if let [.., table, _] = idents.as_slice() {
+                            qualifiers.insert(table.value.clone());
+                        }
+                    }
+                    ControlFlow::<()>::Continue(())
+                });
+                qualifiers.remove(joined);
+                if let Some(left) = join_left_table(&qualifiers, &previous) {
+                    self.join_infos.push(JoinInfo {
+                        left_table: left,
+                        right_table: joined.clone(),
+                        join_type: JoinType::from(&join.join_operator),
+                        columns: join_columns(&join.join_operator),
+                    });
+                }
+                if qualifiers.is_empty() {
+                    qualifiers.extend(previous);
+                }
+                for table in qualifiers {
+                    self.joins.push((table, joined.clone()));
+                }
+            }
+            previous = joined;
+        }
+    }
+
+    // The sequence functions take the sequence name as a string,
+    // e.g. `nextval('my_seq')` or `nextval('my_seq'::regclass)`
+    fn visit_sequence(&mut self, function: &Function) {
+        let name = function.name.to_string().to_lowercase();
+        let is_sequence_function = ["nextval", "currval", "setval"]
+            .iter()
+            .any(|f| name == *f || name.ends_with(&format!(".{f}")));
+        if !is_sequence_function {
+            return;
+        }
+        if let FunctionArguments::List(list) = &function.args {
+            if let Some(FunctionArg::Unnamed(FunctionArgExpr::Expr(expr))) = list.args.first() {
+                let mut expr = expr;
+                while let Expr::Cast { expr: inner, .. } = expr {
+                    expr = inner;
+                }
+                if let Expr::Value(ValueWithSpan {
+                    value: Value::SingleQuotedString(sequence),
+                    ..
+                }) = expr
+                {
+                    self.sequences.insert(sequence.clone());
+                }
+            }
+        }
+    }
+
+    // Collects the CTE names, and the CTEs each CTE reads. Without RECURSIVE a CTE
+    // can only read the ones defined before it, but this is not enforced.
+    fn visit_ctes(&mut self, query: &Query) {
+        let Some(with) = &query.with else {
+            return;
+        };
+        let names: HashSet<String> = with
+            .cte_tables
+            .iter()
+            .map(|cte| cte.alias.name.value.clone())
+            .collect();
+        for cte in &with.cte_tables {
+            let name = &cte.alias.name.value;
+            let _ = visit_relations(&cte.query, |relation| {
+                let relation = object_name(relation);
+                if names.contains(&relation) && relation != *name {
+                    self.cte_dependencies.insert((name.clone(), relation));
+                }
+                ControlFlow::<()>::Continue(())
+            });
+        }
+        self.ctes.extend(names);
+    }
+
+    // In a scalar subquery of an UPDATE value reading a single table, e.g.
+    // `SET total = (SELECT SUM(amount) FROM line_items WHERE order_id = t.id)`,
+    // the unqualified columns belong to that table. The references to the
+    // updated table (`outer`) are the correlated columns.
+    fn visit_assignment_subquery(&mut self, query: &Query, outer: &str) {
+        let SetExpr::Select(select) = query.body.as_ref() else {
+            return;
+        };
+        if let [TableWithJoins {
+            relation: TableFactor::Table { name, .. },
+            joins,
+        }] = select.from.as_slice()
+        {
+            if joins.is_empty() {
+                let mut scope = ScopeColumns::default();
+                let _ = select.visit(&mut scope);
+                for span in scope.spans {
+                    self.subquery_columns.insert(span, object_name(name));
+                }
+            }
+        }
+        let _ = visit_expressions(select, |expr| {
+            if let Expr::CompoundIdentifier(idents) = expr {
+                if let [table, column] = idents.as_slice() {
+                    if table.value == outer {
+                        self.correlated_columns
+                            .insert(format!("{}.{}", table.value, column.value));
+                    }
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+
+    // Collects the GROUP BY keys, skipping the positions (`GROUP BY 1`)
+    fn visit_group_by(&mut self, select: &Select) {
+        let keys: Vec<&Expr> = match &select.group_by {
+            GroupByExpr::Expressions(exprs, _) => exprs.iter().collect(),
+            GroupByExpr::All(_) => select
+                .projection
+                .iter()
+                .filter_map(|item| match item {
+                    SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                        Some(expr)
+                    }
+                    _ => None,
+                })
+                .filter(|expr| !has_aggregate(expr))
+                .collect(),
+        };
+        for key in keys {
+            if literal_u64(key).is_none() {
+                self.group_by.push(key.clone());
+            }
+        }
+    }
+
+    // Flags the projections mixing bare columns and aggregates without a GROUP BY
+    fn visit_aggregation(&mut self, select: &Select) {
+        if !matches!(&select.group_by, GroupByExpr::Expressions(exprs, _) if exprs.is_empty()) {
+            return;
+        }
+        let exprs: Vec<&Expr> = select
+            .projection
+            .iter()
+            .filter_map(|item| match item {
+                SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                    Some(expr)
+                }
+                _ => None,
+            })
+            .collect();
+        let bare_column = exprs
+            .iter()
+            .any(|expr| matches!(expr, Expr::Identifier(_) | Expr::CompoundIdentifier(_)));
+        if bare_column && exprs.iter().any(|expr| has_aggregate(expr)) {
+            self.invalid_aggregation = true;
+        }
+    }
+
+    // `ORDER BY` can refer to the output columns by their alias, also after a set
+    // operation (`SELECT a AS k FROM x UNION SELECT b FROM y ORDER BY k`), where
+    // the names come from the first query. These are not columns of the tables.
+    fn visit_order_by(&mut self, query: &Query) {
+        let Some(OrderBy {
+            kind: OrderByKind::Expressions(order_by),
+            ..
+        }) = &query.order_by
+        else {
+            return;
+        };
+        for order_by_expr in order_by {
+            self.order_by
+                .push((order_by_expr.expr.clone(), order_by_expr.options.asc));
+        }
+        let mut body = query.body.as_ref();
+        while let SetExpr::SetOperation { left, .. } = body {
+            body = left;
+        }
+        let SetExpr::Select(select) = body else {
+            return;
+        };
+        let aliases: HashSet<&str> = select
+            .projection
+            .iter()
+            .filter_map(|item| match item {
+                SelectItem::ExprWithAlias { alias, .. } => Some(alias.value.as_str()),
+                _ => None,
+            })
+            .collect();
+        for order_by_expr in order_by {
+            if let Expr::Identifier(ident) = &order_by_expr.expr {
+                if aliases.contains(ident.value.as_str()) {
+                    self.output_references.insert(ident.span);
+                }
+            }
+        }
+    }
+
+    // Checks that the GROUP BY/ORDER BY positions (e.g. `ORDER BY 2`) match a
+    // projected column. With a wildcard, the number of columns is unknown.
+    fn visit_positions(&mut self, query: &Query) {
+        let SetExpr::Select(select) = query.body.as_ref() else {
+            return;
+        };
+        let wildcard = select.projection.iter().any(|item| {
+            matches!(
+                item,
+                SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)
+            )
+        });
+        if wildcard {
+            return;
+        }
+        let mut exprs: Vec<&Expr> = vec![];
+        if let GroupByExpr::Expressions(group_by, _) = &select.group_by {
+            exprs.extend(group_by);
+        }
+        if let Some(OrderBy {
+            kind: OrderByKind::Expressions(order_by),
+            ..
+        }) = &query.order_by
+        {
+            exprs.extend(order_by.iter().map(|o| &o.expr));
+        }
+        for expr in exprs {
+            if let Some(position) = literal_u64(expr) {
+                if position == 0 || position > select.projection.len() as u64 {
+                    self.warnings.insert(Warning::UnresolvedPosition(position));
+                }
+            }
+        }
+    }
+
+    // Collects the LIMIT/OFFSET values, including the standard
+    // `OFFSET n ROWS FETCH { FIRST | NEXT } m ROWS ONLY` form
+    fn visit_pagination(&mut self, query: &Query) {
+        match &query.limit_clause {
+            Some(LimitClause::LimitOffset { limit, offset, .. }) => {
+                self.has_limit = limit.is_some();
+                self.limit = limit.as_ref().and_then(literal_u64);
+                self.offset = offset.as_ref().and_then(|o| literal_u64(&o.value));
+            }
+            Some(LimitClause::OffsetCommaLimit { offset, limit }) => {
+                self.has_limit = true;
+                self.limit = literal_u64(limit);
+                self.offset = literal_u64(offset);
+            }
+            None => {}
+        }
+        if let Some(fetch) = &query.fetch {
+            // `FETCH FIRST n PERCENT` is not a row count
+            self.has_limit = true;
+            if !fetch.percent {
+                self.limit = fetch.quantity.as_ref().and_then(literal_u64);
+            }
+        }
+    }
+
+    // Collects the columns of the query projection
+    fn visit_projection(&mut self, query: &Query) {
+        if let SetExpr::Select(select) = (query.body).as_ref() {
+            match &select.distinct {
+                Some(Distinct::Distinct) => self.distinct = true,
+                Some(Distinct::On(exprs)) => {
+                    self.distinct = true;
+                    self.distinct_on = exprs.clone();
+                }
+                None => {}
+            }
+            for select_item in &select.projection {
+                let column = match select_item {
+                    SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                        match expr {
+                            Expr::Identifier(ident) => Some(ident.value.clone()),
+                            // This is a compound identifier, like table.column
+                            Expr::CompoundIdentifier(ident) => Some(join(ident)),
+                            _ => None,
+                        }
+                    }
+                    SelectItem::Wildcard(_) => {
+                        self.wildcard_sources.push(select_sources(select));
+                        Some("*".to_string())
+                    }
+                    // `u.*`, resolved to `users.*` like the qualified columns. The
+                    // table itself is collected from the FROM clause.
+                    SelectItem::QualifiedWildcard(
+                        SelectItemQualifiedWildcardKind::ObjectName(name),
+                        _,
+                    ) => Some(format!("{}.*", object_name(name))),
+                    _ => None,
+                };
+                if let Some(column) = column {
+                    self.columns.insert(column.clone());
+                    self.projection.push(column);
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::assigning_clones)]
+impl Visitor for V {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, _stmt: &Statement) -> ControlFlow<Self::Break> {
+        // Only the outermost statement sets the type: `EXPLAIN SELECT ...` is not a SELECT
+        let outermost = self.statement_depth == 0;
+        self.statement_depth += 1;
+        if outermost {
+            self.query_type = statement_query_type(_stmt);
+        } else if self.query_type == QueryType::CREATE_TRIGGER {
+            // A statement of the trigger body: only its tables and columns are kept,
+            // the trigger remains the inspected statement
+            let mut body = self.nested();
+            let _ = body.pre_visit_statement(_stmt);
+            self.tables.extend(body.tables);
+            self.columns.extend(body.columns);
+            return ControlFlow::Continue(());
+        }
+        match _stmt {
+            Statement::Query(q) => {
+                // `SELECT ... INTO t` creates the table, so it can't run twice
+                self.select_into =
+                    matches!(q.body.as_ref(), SetExpr::Select(select) if select.into.is_some());
+                self.idempotent = !self.select_into;
+                // `FOR UPDATE` wins over `FOR SHARE` when both are present
+                if let Some(lock) = q
+                    .locks
+                    .iter()
+                    .find(|l| l.lock_type == LockType::Update)
+                    .or(q.locks.first())
+                {
+                    self.locking = Some(format!("FOR {}", lock.lock_type));
+                }
+                for lock in &q.locks {
+                    if let Some(of) = &lock.of {
+                        self.locked_tables.insert(object_name(of));
+                    }
+                }
+                self.visit_projection(q);
+                self.visit_pagination(q);
+                self.blast_radius = query_radius(q);
+            }
+            Statement::Insert(i) => {
+                // The "insert" statement has a table as a target
+                let table_name = match &i.table {
+                    TableObject::TableName(name) => object_name(name),
+                    table => table.to_string(),
+                };
+                self.tables.insert(table_name.clone());
+                self.target_table = table_name.clone();
+                if let TableObject::TableName(name) = &i.table {
+                    self.target_schema = table_schema(name);
+                    self.target_name = Some(object_name(name));
+                }
+                if let Some(returning) = &i.returning {
+                    self.returning = returning_items(returning);
+                }
+                for i in &i.columns {
+                    let full_name = match self.bare_insert_columns {
+                        true => i.value.clone(),
+                        false => format!("{table_name}.{}", i.value),
+                    };
+                    self.columns.insert(full_name);
+                }
+                // The wildcard of `INSERT INTO t SELECT * FROM s` is not an expression,
+                // so `pre_visit_expr` doesn't see it
+                if let Some(source) = &i.source {
+                    if let SetExpr::Select(select) = source.body.as_ref() {
+                        if select
+                            .projection
+                            .iter()
+                            .any(|item| matches!(item, SelectItem::Wildcard(_)))
+                        {
+                            self.columns.insert("*".to_string());
+                            self.wildcard_sources.push(select_sources(select));
+                        }
+                        let targets =
+                            i.columns
+                                .iter()
+                                .map(|column| match self.bare_insert_columns {
+                                    true => column.value.clone(),
+                                    false => format!("{table_name}.{}", column.value),
+                                });
+                        self.lineage = insert_lineage(targets, select);
+                    }
+                }
+                // `INSERT OVERWRITE` replaces the table content
+                self.overwrite = i.overwrite;
+                // A plain insert adds the rows again at every run, unless conflicts are
+                // handled (`ON CONFLICT`, `ON DUPLICATE KEY UPDATE`, `INSERT IGNORE`, `REPLACE`)
+                self.idempotent = i.overwrite || i.on.is_some() || i.ignore || i.replace_into;
+                // The conflict target and the updated columns of an upsert
+                let (conflict_columns, assignments) = match &i.on {
+                    Some(OnInsert::OnConflict(on_conflict)) => {
+                        let columns = match &on_conflict.conflict_target {
+                            Some(ConflictTarget::Columns(columns)) => columns.as_slice(),
+                            _ => &[],
+                        };
+                        let assignments = match &on_conflict.action {
+                            OnConflictAction::DoUpdate(update) => update.assignments.as_slice(),
+                            OnConflictAction::DoNothing => &[],
+                        };
+                        (columns, assignments)
+                    }
+                    Some(OnInsert::DuplicateKeyUpdate(assignments)) => (&[][..], &assignments[..]),
+                    _ => (&[][..], &[][..]),
+                };
+                self.has_upsert = matches!(
+                    i.on,
+                    Some(OnInsert::OnConflict(_) | OnInsert::DuplicateKeyUpdate(_))
+                );
+                let updated =
+                    assignments
+                        .iter()
+                        .filter_map(|assignment| match &assignment.target {
+                            AssignmentTarget::ColumnName(name) => name.0.last()?.as_ident(),
+                            AssignmentTarget::Tuple(_) => None,
+                        });
+                for column in conflict_columns.iter().chain(updated) {
+                    let full_name = match self.bare_insert_columns {
+                        true => column.value.clone(),
+                        false => format!("{table_name}.{}", column.value),
+                    };
+                    self.columns.insert(full_name);
+                }
+                self.blast_radius = i.source.as_deref().and_then(query_radius);
+                // MySQL 8.0.20 deprecated `VALUES(col)` to refer to the inserted row
+                if let Some(OnInsert::DuplicateKeyUpdate(assignments)) = &i.on {
+                    let uses_values = assignments.iter().any(|assignment| {
+                        visit_expressions(&assignment.value, |expr| match expr {
+                            Expr::Function(f)
+                                if f.name.to_string().eq_ignore_ascii_case("values") =>
+                            {
+                                ControlFlow::Break(())
+                            }
+                            _ => ControlFlow::Continue(()),
+                        })
+                        .is_break()
+                    });
+                    if uses_values {
+                        self.warnings.insert(Warning::DeprecatedSyntax(
+                            "VALUES() in ON DUPLICATE KEY UPDATE".to_string(),
+                        ));
+                    }
+                }
+            }
+            Statement::Update {
+                table,
+                assignments,
+                from,
+                selection,
+                returning,
+                or: _,
+            } => {
+                if let Some(returning) = returning {
+                    self.returning = returning_items(returning);
+                }
+                // There's no main query, any query is a subquery
+                self.scope_depth = 1;
+                self.visit_table_with_joins(table);
+                if let Some(selection) = selection {
+                    self.predicate_count += count_predicates(selection);
+                    self.visit_filter(selection);
+                }
+                self.blast_radius = filter_radius(selection.as_ref());
+                if from.is_some() || !table.joins.is_empty() {
+                    self.visit_ambiguous(assignments);
+                    self.visit_ambiguous(selection);
+                }
+                // The "insert" statement has a table as a target
+                let table_name = table_factor_name(&table.relation)
+                    .unwrap_or_else(|| table.relation.to_string());
+                self.target_table = table_name.clone();
+                self.target_schema = table_factor_schema(&table.relation);
+                self.target_name = table_factor_name(&table.relation);
+                // Values computed from columns (e.g. `SET n = n + 1`) can change at every run
+                self.idempotent = !assignments.iter().any(|assignment| {
+                    visit_expressions(&assignment.value, |expr| match expr {
+                        Expr::Identifier(_) | Expr::CompoundIdentifier(_) => ControlFlow::Break(()),
+                        _ => ControlFlow::Continue(()),
+                    })
+                    .is_break()
+                });
+                if let Some(outer) = table_factor_reference(&table.relation) {
+                    for assignment in assignments {
+                        let _ = visit_expressions(&assignment.value, |expr| {
+                            if let Expr::Subquery(query) = expr {
+                                self.visit_assignment_subquery(query, &outer);
+                            }
+                            ControlFlow::<()>::Continue(())
+                        });
+                    }
+                }
+                for assignment in assignments {
+                    let value = assignment.value.clone();
+                    let target = assignment.target.clone();
+                    match value {
+                        Expr::CompoundIdentifier(ident) => {
+                            // This is a compound identifier, like table.column
+                            if let (Some(first), Some(second)) = (ident.first(), ident.last()) {
+                                let full_name = format!("{}.{}", first.value, second.value);
+                                self.columns.insert(full_name);
+                            }
+                        }
+                        Expr::Identifier(ident) => {
+                            let full_name = format!("{table_name}.{}", ident.value);
+                            self.columns.insert(full_name);
+                        }
+                        _ => {}
+                    }
+                    if let AssignmentTarget::ColumnName(ident) = target {
+                        // It's a tuple with one vector of idents
+                        if let [_] = ident.0.as_slice() {
+                            let full_name = format!("{table_name}.{}", object_name(&ident));
+                            self.columns.insert(full_name);
+                        } else {
+                            // Convert ObjectNameParts to Idents for join function
+                            let idents: Vec<Ident> = ident
+                                .0
+                                .iter()
+                                .filter_map(|part| match part {
+                                    ObjectNamePart::Identifier(ident) => Some(ident.clone()),
+                                    ObjectNamePart::Function(_) => None,
+                                })
+                                .collect();
+                            let full_name = join(&idents);
+                            self.columns.insert(full_name);
+                        }
+                    }
+                }
+                self.tables.insert(table_name);
+            }
+            Statement::CreateView {
+                name,
+                query,
+                materialized,
+                ..
+            } => {
+                // The view is both a table and the target of the statement
+                let view_name = object_name(name);
+                self.tables.insert(view_name.clone());
+                self.target_table = view_name;
+                self.materialized = *materialized;
+                self.visit_projection(query);
+            }
+            Statement::LockTables { tables } => {
+                self.idempotent = true;
+                for lock in tables {
+                    let table_name = lock.table.value.clone();
+                    self.tables.insert(table_name.clone());
+                    if let Some(alias) = &lock.alias {
+                        self.aliases.insert(alias.value.clone(), table_name.clone());
+                    }
+                    self.locks.push((table_name, lock.lock_type.to_string()));
+                }
+            }
+            Statement::Call(function) => {
+                // The procedure body is unknown, so it's not idempotent.
+                // The columns of the arguments are collected by `pre_visit_expr`
+                self.procedures.insert(object_name(&function.name));
+            }
+            Statement::Merge { table, clauses, .. } => {
+                // There's no main query, the source can be a subquery
+                self.scope_depth = 1;
+                // The target table and its alias are collected like the source's,
+                // as table factors
+                let table_name = table_factor_name(table).unwrap_or_else(|| table.to_string());
+                self.target_table = table_name.clone();
+                self.target_schema = table_factor_schema(table);
+                self.target_name = table_factor_name(table);
+                for clause in clauses {
+                    match &clause.action {
+                        MergeAction::Insert(insert) => {
+                            for column in &insert.columns {
+                                let full_name = match self.bare_insert_columns {
+                                    true => column.value.clone(),
+                                    false => format!("{table_name}.{}", column.value),
+                                };
+                                self.columns.insert(full_name);
+                            }
+                        }
+                        MergeAction::Update { assignments } => {
+                            for assignment in assignments {
+                                if let AssignmentTarget::ColumnName(name) = &assignment.target {
+                                    let full_name = match name.0.as_slice() {
+                                        [_] => format!("{table_name}.{}", object_name(name)),
+                                        _ => object_name(name),
+                                    };
+                                    self.columns.insert(full_name);
+                                }
+                            }
+                        }
+                        MergeAction::Delete => {}
+                    }
+                }
+            }
+            Statement::CreateTrigger { table_name, .. } => {
+                // The triggering table is the target, the tables of the body
+                // statements (when parsed, e.g. MsSql) are collected too
+                let table = object_name(table_name);
+                self.tables.insert(table.clone());
+                self.target_table = table;
+                self.target_schema = table_schema(table_name);
+                self.target_name = Some(object_name(table_name));
+            }
+            Statement::CreateTable(create) => {
+                // The new table is the target, its columns are the defined ones.
+                // With `CREATE TABLE ... AS SELECT`, the tables and columns of the query
+                // are collected too.
+                let table_name = object_name(&create.name);
+                self.tables.insert(table_name.clone());
+                self.target_table = table_name.clone();
+                self.target_schema = table_schema(&create.name);
+                self.target_name = Some(object_name(&create.name));
+                for column in &create.columns {
+                    let full_name = format!("{table_name}.{}", column.name.value);
+                    self.columns.insert(full_name.clone());
+                    self.column_types
+                        .insert(full_name, column.data_type.to_string());
+                }
+            }
+            Statement::AlterTable {
+                name, operations, ..
+            } => {
+                // The columns added, dropped, renamed (the old and the new names)
+                // or modified by the operations
+                let table_name = object_name(name);
+                self.target_table = table_name.clone();
+                self.target_schema = table_schema(name);
+                self.target_name = Some(object_name(name));
+                let mut columns: Vec<&Ident> = vec![];
+                for operation in operations {
+                    match operation {
+                        AlterTableOperation::AddColumn { column_def, .. } => {
+                            let full_name = format!("{table_name}.{}", column_def.name.value);
+                            self.column_types
+                                .insert(full_name, column_def.data_type.to_string());
+                            columns.push(&column_def.name);
+                        }
+                        AlterTableOperation::DropColumn { column_names, .. } => {
+                            columns.extend(column_names);
+                        }
+                        AlterTableOperation::RenameColumn {
+                            old_column_name,
+                            new_column_name,
+                        } => columns.extend([old_column_name, new_column_name]),
+                        AlterTableOperation::ChangeColumn {
+                            old_name, new_name, ..
+                        } => columns.extend([old_name, new_name]),
+                        AlterTableOperation::ModifyColumn { col_name, .. } => {
+                            columns.push(col_name)
+                        }
+                        AlterTableOperation::AlterColumn { column_name, .. } => {
+                            columns.push(column_name)
+                        }
+                        AlterTableOperation::RenameTable { table_name } => {
+                            self.tables.insert(object_name(table_name));
+                        }
+                        _ => {}
+                    }
+                }
+                for column in columns {
+                    self.columns
+                        .insert(format!("{table_name}.{}", column.value));
+                }
+            }
+            Statement::Drop {
+                object_type,
+                names,
+                table,
+                ..
+            } => {
+                self.object_type = Some(object_type.to_string());
+                if let Some(name) = names.first() {
+                    self.target_table = object_name(name);
+                    self.target_schema = table_schema(name);
+                    self.target_name = Some(object_name(name));
+                }
+                // The dropped tables and views, or the table of the dropped index
+                // (`DROP INDEX idx ON t`). The other objects (e.g. a schema) are not tables.
+                match object_type {
+                    ObjectType::Table | ObjectType::View | ObjectType::MaterializedView => {
+                        self.tables.extend(names.iter().map(object_name));
+                    }
+                    _ => self.tables.extend(table.iter().map(object_name)),
+                }
+            }
+            Statement::Delete(delete) => {
+                // There's no main query, any query is a subquery
+                self.scope_depth = 1;
+                // Deleting the same rows twice has the same effect as once
+                self.idempotent = true;
+                if let Some(selection) = &delete.selection {
+                    self.predicate_count += count_predicates(selection);
+                    self.visit_filter(selection);
+                }
+                self.blast_radius = filter_radius(delete.selection.as_ref());
+                let tables = match &delete.from {
+                    FromTable::WithFromKeyword(tables) | FromTable::WithoutKeyword(tables) => {
+                        tables
+                    }
+                };
+                let table_count: usize = tables.iter().map(|t| 1 + t.joins.len()).sum();
+                if table_count > 1 || delete.using.is_some() {
+                    self.visit_ambiguous(&delete.selection);
+                }
+                if let Some(returning) = &delete.returning {
+                    self.returning = returning_items(returning);
+                }
+                if let FromTable::WithFromKeyword(tables) = &delete.from {
+                    if let Some(first) = tables.first() {
+                        self.target_table = table_factor_name(&first.relation)
+                            .unwrap_or_else(|| first.relation.to_string());
+                        self.target_schema = table_factor_schema(&first.relation);
+                        self.target_name = table_factor_name(&first.relation);
+                    }
+                    // In mysql, the FROM clause can have multiple tables
+                    for i in tables {
+                        self.tables.extend(table_factor_name(&i.relation));
+                    }
+                }
+            }
+
+            _ => {
+                // Keep the kind of the first statement we don't handle, e.g. `CreateIndex`
+                if self.unsupported.is_none() {
+                    self.unsupported = Some(statement_kind(_stmt));
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_statement(&mut self, _stmt: &Statement) -> ControlFlow<Self::Break> {
+        self.statement_depth -= 1;
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        if self.scope_depth > 0 {
+            self.subquery_count += 1;
+        }
+        self.visit_ctes(query);
+        self.visit_table_expr(&query.body);
+        self.visit_selects(&query.body);
+        self.visit_positions(query);
+        self.visit_order_by(query);
+        self.scope_depth += 1;
+        self.max_depth = self.max_depth.max(self.scope_depth - 1);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        self.scope_depth -= 1;
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, _table_factor: &TableFactor) -> ControlFlow<Self::Break> {
+        // Here we extract aliases for table names
+        match _table_factor {
+            TableFactor::JsonTable {
+                json_expr,
+                columns,
+                alias,
+                ..
+            } => {
+                self.json_tables.push(JsonTable {
+                    alias: alias.as_ref().map(|a| a.name.value.clone()),
+                    source: column_name(json_expr).unwrap_or_else(|| json_expr.to_string()),
+                    columns: json_table_columns(columns),
+                });
+            }
+            TableFactor::OpenJsonTable {
+                json_expr,
+                columns,
+                alias,
+                ..
+            } => {
+                self.json_tables.push(JsonTable {
+                    alias: alias.as_ref().map(|a| a.name.value.clone()),
+                    source: column_name(json_expr).unwrap_or_else(|| json_expr.to_string()),
+                    columns: columns.iter().map(|c| c.name.value.clone()).collect(),
+                });
+            }
+            // A subquery in FROM, not a physical table
+            TableFactor::Derived {
+                alias: Some(alias), ..
+            } => {
+                self.derived_tables.insert(alias.name.value.clone());
+            }
+            _ => {}
+        }
+        if let TableFactor::Table {
+            name, alias, args, ..
+        } = _table_factor
+        {
+            let table_name = object_name(name);
+            self.tables.insert(table_name.clone());
+            if args.is_some() {
+                self.table_functions.insert(table_name.clone());
+            }
+            if let Some(alias) = alias {
+                let alias = alias.name.value.clone();
+                self.aliases.insert(alias, table_name);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<Self::Break> {
+        // Relation === table name, possibly qualified (e.g. `db.table`)
+        let parts: Vec<&str> = relation
+            .0
+            .iter()
+            .filter_map(|part| match part {
+                ObjectNamePart::Identifier(ident) => Some(ident.value.as_str()),
+                ObjectNamePart::Function(_) => None,
+            })
+            .collect();
+        if let Some(first) = parts.first() {
+            self.see(parts.join("."));
+            self.tables.insert(parts.join("."));
+            // The first part of `db.schema.table` is the database, and so is the first part
+            // of `db.table` in MySQL, where a database is a schema. Elsewhere, `public.orders`
+            // is qualified by a schema.
+            if parts.len() > 2 || (parts.len() == 2 && self.database_qualified) {
+                self.databases.insert(first.to_string());
+            }
+            // and the part before the table name the schema
+            self.schemas.extend(table_schema(relation));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        self.visit_predicate(expr);
+        if let Expr::Function(function) = expr {
+            // The columns of the arguments are collected as expressions too
+            self.function_calls += 1;
+            self.functions
+                .insert(object_name(&function.name).to_uppercase());
+            // The PARTITION BY and ORDER BY columns of `OVER` are collected as expressions too
+            if function.over.is_some() {
+                self.window_functions
+                    .insert(object_name(&function.name).to_uppercase());
+            }
+            self.visit_sequence(function);
+        }
+        if let Expr::Wildcard(_) = expr {
+            self.columns.insert("*".to_string());
+        }
+        if let Expr::Value(ValueWithSpan {
+            value: Value::Placeholder(placeholder),
+            ..
+        }) = expr
+        {
+            self.parameters.push(placeholder.clone());
+        }
+        if let Expr::Identifier(ident) = expr {
+            if let Some(table) = self.subquery_columns.get(&ident.span) {
+                self.columns.insert(format!("{table}.{}", ident.value));
+            } else if !self.output_references.contains(&ident.span) {
+                self.see(ident.value.clone());
+                self.columns.insert(ident.value.clone());
+            }
+        }
+
+        if let Expr::CompoundIdentifier(idents) = expr {
+            let mut full_column = String::new();
+            for ident in idents {
+                full_column.push_str(&ident.value);
+                full_column.push('.');
+            }
+            full_column.pop();
+            self.see(full_column.clone());
+            self.columns.insert(full_column);
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+// Extracts the tables and columns of the query, parsed with the SQL dialect
+// named by `dialect` (see `dialect_from_str`)
+pub fn inspect(sql: &str, dialect: &str) -> Result<ExtractResult, InspectError> {
+    let options = InspectOptions {
+        dialect: dialect_from_str(dialect),
+        ..Default::default()
+    };
+    inspect_with(sql, &options)
+}
+
+// Maps a dialect name (`postgres`, `mysql`, `sqlite`, `mssql`, `ansi`, `snowflake` or `generic`,
+// case-insensitive) to the dialect. Unknown names fall back to the generic dialect.
+pub fn dialect_from_str(name: &str) -> Dialect {
+    match name.to_lowercase().as_str() {
+        "postgres" | "postgresql" => Dialect::Postgres,
+        "mysql" => Dialect::MySql,
+        "sqlite" => Dialect::Sqlite,
+        "mssql" => Dialect::MsSql,
+        "ansi" => Dialect::Ansi,
+        "snowflake" => Dialect::Snowflake,
+        _ => Dialect::Generic,
+    }
+}
+
+// The tokens of the SQL, with their location
+fn tokenize(sql: &str, dialect: Dialect) -> Result<Vec<TokenWithSpan>, InspectError> {
+    Tokenizer::new(dialect.parser_dialect().as_ref(), sql)
+        .tokenize_with_location()
+        .map_err(|error| {
+            let error = ParserError::from(error);
+            let snippet = error_snippet(sql, &error);
+            InspectError::Parse { error, snippet }
+        })
+}
+
+fn parse(sql: &str, dialect: Dialect) -> Result<Vec<Statement>, InspectError> {
+    Parser::parse_sql(dialect.parser_dialect().as_ref(), sql).map_err(|error| {
+        let snippet = error_snippet(sql, &error);
+        InspectError::Parse { error, snippet }
+    })
+}
+
+// The word of the SQL where the parser failed, from the location in the error message
+// (e.g. `SELCT` for "found: SELCT at Line: 1, Column: 1"). Errors at the end of the
+// input have no location, so the last word is used.
+fn error_snippet(sql: &str, error: &ParserError) -> String {
+    let message = error.to_string();
+    let location = message.rsplit_once(" at Line: ").and_then(|(_, location)| {
+        let (line, column) = location.split_once(", Column: ")?;
+        Some((line.parse::<usize>().ok()?, column.parse::<usize>().ok()?))
+    });
+    let rest = location.and_then(|(line, column)| {
+        let line = sql.lines().nth(line.checked_sub(1)?)?;
+        let start = line.char_indices().nth(column.checked_sub(1)?)?.0;
+        Some(&line[start..])
+    });
+    let word = match rest {
+        Some(rest) => rest.split_whitespace().next(),
+        None => sql.split_whitespace().last(),
+    };
+    word.unwrap_or_default().chars().take(32).collect()
+}
+
+pub fn inspect_with(sql: &str, options: &InspectOptions) -> Result<ExtractResult, InspectError> {
+    // With many statements, only the first one is inspected (see `inspect_all`)
+    let results = inspect_statements(sql, options, Statements::First, None)?;
+    Ok(first_result(results, options))
+}
+
+// A bounded cache of the `inspect` results by query, for the hot paths inspecting
+// the same queries again and again. When full, the least recently used result is
+// evicted. The errors are not cached.
+#[derive(Debug, Clone)]
+pub struct InspectCache {
+    dialect: Dialect,
+    capacity: usize,
+    // Incremented at every use
+    clock: u64,
+    // The results by query, with the clock of their last use
+    results: HashMap<String, (ExtractResult, u64)>,
+    // The queries by clock of their last use, the least recently used first
+    uses: BTreeMap<u64, String>,
+}
+
+impl InspectCache {
+    // A cache of at most `capacity` results of the queries parsed with the SQL
+    // dialect named by `dialect` (see `dialect_from_str`)
+    pub fn new(dialect: &str, capacity: usize) -> Self {
+        InspectCache {
+            dialect: dialect_from_str(dialect),
+            capacity,
+            clock: 0,
+            results: HashMap::with_capacity(capacity),
+            uses: BTreeMap::new(),
+        }
+    }
+
+    // Like `inspect`, returning a copy of the cached result when the query was seen before
+    pub fn inspect(&mut self, sql: &str) -> Result<ExtractResult, InspectError> {
+        self.clock += 1;
+        if let Some((result, last_use)) = self.results.get_mut(sql) {
+            if let Some(query) = self.uses.remove(last_use) {
+                self.uses.insert(self.clock, query);
+            }
+            *last_use = self.clock;
+            return Ok(result.clone());
+        }
+        let options = InspectOptions {
+            dialect: self.dialect,
+            ..Default::default()
+        };
+        let result = inspect_with(sql, &options)?;
+        if self.capacity > 0 {
+            if self.results.len() == self.capacity {
+                if let Some((_, query)) = self.uses.pop_first() {
+                    self.results.remove(&query);
+                }
+            }
+            self.results
+                .insert(sql.to_string(), (result.clone(), self.clock));
+            self.uses.insert(self.clock, sql.to_string());
+        }
+        Ok(result)
+    }
+
+    // True when the result of the query is cached
+    pub fn contains(&self, sql: &str) -> bool {
+        self.results.contains_key(sql)
+    }
+
+    // The number of cached results
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+}
+
+// Like `inspect`, resolving the tables the views depend on through their
+// definitions (view name -> defining query)
+pub fn inspect_with_views(
+    sql: &str,
+    views: &HashMap<String, String>,
+) -> Result<ExtractResult, InspectError> {
+    let options = InspectOptions {
+        views: views.clone(),
+        ..Default::default()
+    };
+    inspect_with(sql, &options)
+}
+
+// Like `inspect`, replacing the projected `*` with the columns of the tables
+// (table name -> column names)
+pub fn inspect_with_schema(
+    sql: &str,
+    schema: &HashMap<String, Vec<String>>,
+) -> Result<ExtractResult, InspectError> {
+    let options = InspectOptions {
+        schema: schema.clone(),
+        expand_wildcards: true,
+        ..Default::default()
+    };
+    inspect_with(sql, &options)
+}
+
+// A source of table columns, e.g. a cache in front of a remote catalog,
+// used to expand the wildcards. See `inspect_with_provider`.
+pub trait SchemaProvider {
+    // The columns of the table, `None` when the table is unknown
+    fn columns_of(&self, table: &str) -> Option<Vec<String>>;
+}
+
+impl<S: std::hash::BuildHasher> SchemaProvider for HashMap<String, Vec<String>, S> {
+    fn columns_of(&self, table: &str) -> Option<Vec<String>> {
+        self.get(table).cloned()
+    }
+}
+
+// Like `inspect_with`, expanding the wildcards with the columns of a `SchemaProvider`
+// (instead of the `schema` of the options)
+pub fn inspect_with_provider(
+    sql: &str,
+    provider: &dyn SchemaProvider,
+    options: &InspectOptions,
+) -> Result<ExtractResult, InspectError> {
+    let results = inspect_statements(sql, options, Statements::First, Some(provider))?;
+    Ok(first_result(results, options))
+}
+
+// sqlparser doesn't support the Postgres
+// `REFRESH MATERIALIZED VIEW [ CONCURRENTLY ] name [ WITH [ NO ] DATA ]`
+// statement, so we recognize it from the tokens and return the view name
+fn refresh_target(sql: &str, options: &InspectOptions) -> Option<String> {
+    let dialect = options.dialect.parser_dialect();
+    let tokens = Tokenizer::new(dialect.as_ref(), sql).tokenize().ok()?;
+    let mut tokens = tokens
+        .into_iter()
+        .filter(|t| !matches!(t, Token::Whitespace(_)))
+        .peekable();
+    let is_word = |token: Option<&Token>, word: &str| matches!(token, Some(Token::Word(w)) if w.value.eq_ignore_ascii_case(word));
+    for keyword in ["REFRESH", "MATERIALIZED", "VIEW"] {
+        if !is_word(tokens.next().as_ref(), keyword) {
+            return None;
+        }
+    }
+    if is_word(tokens.peek(), "CONCURRENTLY") {
+        tokens.next();
+    }
+
+    let mut name = ObjectName(vec![]);
+    while let Some(Token::Word(word)) = tokens.next_if(|t| matches!(t, Token::Word(_))) {
+        let ident = match word.quote_style {
+            Some(quote) => Ident::with_quote(quote, word.value),
+            None => Ident::new(word.value),
+        };
+        name.0.push(ObjectNamePart::Identifier(ident));
+        if tokens.next_if_eq(&Token::Period).is_none() {
+            break;
+        }
+    }
+
+    // Only the `WITH [ NO ] DATA` clause can follow
+    let rest = tokens
+        .filter(|t| *t != Token::SemiColon)
+        .map(|t| t.to_string().to_uppercase())
+        .collect::<Vec<String>>()
+        .join(" ");
+    if name.0.is_empty() || !["", "WITH DATA", "WITH NO DATA"].contains(&rest.as_str()) {
+        return None;
+    }
+    let casing = options.fold_case.resolve(options.dialect);
+    CaseFolder {
+        casing,
+        quotes: false,
+    }
+    .fold_name(&mut name);
+    CaseFolder {
+        casing: Casing::Preserve,
+        quotes: options.preserve_quotes,
+    }
+    .fold_name(&mut name);
+    Some(object_name(&name))
+}
+
+// The lock strengths, from the strongest
+const LOCK_STRENGTHS: [&str; 4] = ["UPDATE", "NO KEY UPDATE", "SHARE", "KEY SHARE"];
+
+// sqlparser doesn't support the Postgres `FOR NO KEY UPDATE` and `FOR KEY SHARE`
+// locking clauses, so we recognize them from the tokens. Returns the input without
+// their `NO KEY` and `KEY` words (parsed as `FOR UPDATE` and `FOR SHARE`), and the
+// strongest locking clause of the input.
+fn key_lock(sql: &str, dialect: Dialect) -> Option<(String, String)> {
+    let tokens = tokenize(sql, dialect).ok()?;
+    let words: Vec<&TokenWithSpan> = tokens
+        .iter()
+        .filter(|t| !matches!(t.token, Token::Whitespace(_)))
+        .collect();
+    let is_word = |i: usize, word: &str| matches!(words.get(i).map(|t| &t.token), Some(Token::Word(w)) if w.value.eq_ignore_ascii_case(word));
+    let offset = byte_offset(sql);
+    let mut removed = vec![];
+    let mut strongest = LOCK_STRENGTHS.len();
+    for i in (0..words.len()).filter(|&i| is_word(i, "FOR")) {
+        let Some(strength) = LOCK_STRENGTHS.iter().position(|strength| {
+            strength
+                .split(' ')
+                .enumerate()
+                .all(|(n, word)| is_word(i + 1 + n, word))
+        }) else {
+            continue;
+        };
+        strongest = strongest.min(strength);
+        // The words before `UPDATE` or `SHARE`
+        let extra = LOCK_STRENGTHS[strength].split(' ').count() - 1;
+        if extra > 0 {
+            removed.push(offset(words[i + 1].span.start)..offset(words[i + 1 + extra].span.start));
+        }
+    }
+    if removed.is_empty() {
+        return None;
+    }
+    let mut sql = sql.to_string();
+    for range in removed.into_iter().rev() {
+        sql.replace_range(range, "");
+    }
+    Some((sql, format!("FOR {}", LOCK_STRENGTHS[strongest])))
+}
+
+// The refresh recomputes the view from its sources, so when its definition is
+// known, the tables it depends on are resolved (through other views too)
+fn inspect_refresh(view: String, options: &InspectOptions) -> ExtractResult {
+    let mut visitor = V {
+        query_type: QueryType::REFRESH,
+        target_table: view.clone(),
+        idempotent: true,
+        ..Default::default()
+    };
+    resolve_view(view, options, &mut vec![], &mut visitor);
+    extract_result(visitor)
+}
+
+// Adds the view and the tables it depends on to the visitor's tables.
+// `path` holds the views being resolved, to detect the circular definitions.
+fn resolve_view(name: String, options: &InspectOptions, path: &mut Vec<String>, visitor: &mut V) {
+    if path.contains(&name) {
+        visitor.warnings.insert(Warning::CircularView(name));
+        return;
+    }
+    if !visitor.tables.insert(name.clone()) {
+        // Already resolved through another view
+        return;
+    }
+    let Some(definition) = options.views.get(&name) else {
+        return;
+    };
+    let dialect = options.dialect.parser_dialect();
+    if let Ok(mut statements) = Parser::parse_sql(dialect.as_ref(), definition) {
+        fold_case(&mut statements, options);
+        let mut sources = V::default();
+        for statement in &statements {
+            let _ = statement.visit(&mut sources);
+        }
+        path.push(name);
+        for source in sources.tables {
+            if !sources.ctes.contains(&source) {
+                resolve_view(source, options, path, visitor);
+            }
+        }
+        path.pop();
+    }
+}
+
+// Writing to a view writes to the tables of its definition: resolves them,
+// through the nested views too
+fn resolve_targets(visitor: &mut V, options: &InspectOptions) {
+    let Some(target) = &visitor.target_name else {
+        return;
+    };
+    if !options.views.contains_key(target) {
+        return;
+    }
+    visitor.resolved_targets = Vec::from_iter(base_tables(target.clone(), options));
+}
+
+// The base tables a view reads, or the table itself when it's not a view: the
+// tables `resolve_view` reaches that are not views
+fn base_tables(name: String, options: &InspectOptions) -> BTreeSet<String> {
+    let mut visitor = V::default();
+    resolve_view(name, options, &mut vec![], &mut visitor);
+    visitor
+        .tables
+        .into_iter()
+        .filter(|table| !options.views.contains_key(table))
+        .collect()
+}
+
+// A statement of the input: parsed, with the locking clause that sqlparser doesn't
+// support (see `key_lock`), or the view of a `REFRESH MATERIALIZED VIEW` (see `refresh_target`)
+enum Parsed {
+    Statement(Box<Statement>, Option<String>),
+    Refresh(String),
+}
+
+// Parses the statements of the input, with their case folded. sqlparser fails on
+// the REFRESH statements and on the key locking clauses, so they are only looked
+// for when the parsing fails: the statements are then parsed one by one.
+fn parse_statements(sql: &str, options: &InspectOptions) -> Result<Vec<Parsed>, InspectError> {
+    let parse_folded = |sql: &str| {
+        let mut statements = parse(sql, options.dialect)?;
+        fold_case(&mut statements, options);
+        Ok(statements
+            .into_iter()
+            .map(|statement| Parsed::Statement(Box::new(statement), None))
+            .collect::<Vec<Parsed>>())
+    };
+    let err = match parse_folded(sql) {
+        Ok(statements) => return Ok(statements),
+        Err(err) => err,
+    };
+    let Ok(tokens) = tokenize(sql, options.dialect) else {
+        return Err(err);
+    };
+    let sources = statement_sources(sql, &tokens);
+    let views: Vec<Option<String>> = sources
+        .iter()
+        .map(|source| refresh_target(source, options))
+        .collect();
+    let locks: Vec<Option<(String, String)>> = sources
+        .iter()
+        .map(|source| key_lock(source, options.dialect))
+        .collect();
+    if views.iter().all(Option::is_none) && locks.iter().all(Option::is_none) {
+        return Err(err);
+    }
+    let mut statements = vec![];
+    for ((source, view), lock) in sources.iter().zip(views).zip(locks) {
+        match (view, lock) {
+            (Some(view), _) => statements.push(Parsed::Refresh(view)),
+            (None, Some((source, locking))) => statements.extend(
+                parse_folded(&source)?
+                    .into_iter()
+                    .map(|parsed| match parsed {
+                        Parsed::Statement(statement, _) => {
+                            Parsed::Statement(statement, Some(locking.clone()))
+                        }
+                        parsed => parsed,
+                    }),
+            ),
+            (None, None) => statements.extend(parse_folded(source)?),
+        }
+    }
+    Ok(statements)
+}
+
+// The statements of the input to inspect
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Statements {
+    // The first one, the others are ignored
+    First,
+    // The only one, failing with `InspectError::MultipleStatements` when there are others
+    Single,
+    All,
+}
+
+// The pipeline shared by the `inspect` functions: one result for each statement.
+// The wildcards are expanded with the `provider` when there's one, otherwise with
+// the `schema` of the options (see `InspectOptions::expand_wildcards`).
+fn inspect_statements(
+    sql: &str,
+    options: &InspectOptions,
+    statements: Statements,
+    provider: Option<&dyn SchemaProvider>,
+) -> Result<Vec<ExtractResult>, InspectError> {
+    let mut parsed = parse_statements(sql, options)?;
+    match statements {
+        Statements::First => parsed.truncate(1),
+        Statements::Single if parsed.len() > 1 => {
+            return Err(InspectError::MultipleStatements(parsed.len()))
+        }
+        _ => {}
+    }
+    let sources = if options.include_source {
+        statement_sources(sql, &tokenize(sql, options.dialect)?)
+    } else {
+        vec![]
+    };
+    parsed
+        .iter()
+        .enumerate()
+        .map(|(index, parsed)| {
+            let mut res = match parsed {
+                Parsed::Refresh(view) => inspect_refresh(view.clone(), options),
+                Parsed::Statement(statement, locking) => {
+                    let mut visitor = V::with_options(options);
+                    let _ = statement.visit(&mut visitor);
+                    if visitor.locking.is_some() && locking.is_some() {
+                        visitor.locking = locking.clone();
+                    }
+                    check_supported(&visitor, options)?;
+                    match provider {
+                        Some(provider) => {
+                            expand_wildcards_with(&mut visitor, provider, options.wildcard_policy)?
+                        }
+                        None => expand_wildcards(&mut visitor, options)?,
+                    }
+                    resolve_targets(&mut visitor, options);
+                    extract_result(visitor)
+                }
+            };
+            res.source = sources.get(index).cloned();
+            Ok(res)
+        })
+        .collect()
+}
+
+// The result of the first statement, or an empty SELECT result when the input
+// has no statement
+fn first_result(results: Vec<ExtractResult>, options: &InspectOptions) -> ExtractResult {
+    results
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| extract_result(V::with_options(options)))
+}
+
+// Like `inspect`, but the input must be a single statement: instead of ignoring
+// the statements after the first, it fails with `InspectError::MultipleStatements`
+pub fn inspect_single(sql: &str) -> Result<ExtractResult, InspectError> {
+    inspect_single_with(sql, &InspectOptions::default())
+}
+
+pub fn inspect_single_with(
+    sql: &str,
+    options: &InspectOptions,
+) -> Result<ExtractResult, InspectError> {
+    let results = inspect_statements(sql, options, Statements::Single, None)?;
+    Ok(first_result(results, options))
+}
+
+// Returns the type of the first statement, parsed with the SQL dialect named by
+// `dialect`, without extracting the tables and columns: the `query_type` of `inspect`
+pub fn query_type(sql: &str, dialect: &str) -> Result<QueryType, InspectError> {
+    let options = InspectOptions {
+        dialect: dialect_from_str(dialect),
+        ..Default::default()
+    };
+    match parse_statements(sql, &options)?.first() {
+        Some(Parsed::Statement(statement, _)) => Ok(statement_query_type(statement)),
+        Some(Parsed::Refresh(_)) => Ok(QueryType::REFRESH),
+        None => Ok(QueryType::SELECT),
+    }
+}
+
+// The type of a statement, `UNSUPPORTED` when the visitor doesn't handle it
+fn statement_query_type(statement: &Statement) -> QueryType {
+    match statement {
+        // `WITH a AS (...) INSERT INTO ...` is an INSERT
+        Statement::Query(query) => match query.body.as_ref() {
+            SetExpr::Insert(statement)
+            | SetExpr::Update(statement)
+            | SetExpr::Delete(statement) => statement_query_type(statement),
+            _ => QueryType::SELECT,
+        },
+        Statement::Insert(_) => QueryType::INSERT,
+        Statement::Update { .. } => QueryType::UPDATE,
+        Statement::Delete(_) => QueryType::DELETE,
+        Statement::CreateView { .. } => QueryType::CREATE_VIEW,
+        Statement::LockTables { .. } => QueryType::LOCK,
+        Statement::Call(_) => QueryType::CALL,
+        Statement::CreateTrigger { .. } => QueryType::CREATE_TRIGGER,
+        Statement::Merge { .. } => QueryType::MERGE,
+        Statement::CreateTable(_) => QueryType::CREATE_TABLE,
+        Statement::AlterTable { .. } => QueryType::ALTER_TABLE,
+        Statement::Drop { .. } => QueryType::DROP,
+        _ => QueryType::UNSUPPORTED,
+    }
+}
+
+// Returns the deduped table -> table edges of the statements, parsed with the SQL
+// dialect named by `dialect`, sorted: from every source table to the target of a
+// write (INSERT, UPDATE, MERGE, CREATE VIEW, CREATE TABLE ... AS SELECT), and
+// between the joined tables. Join edges are undirected, so their pair is sorted.
+// The CTEs are not tables, so they are not in the edges.
+pub fn table_edges(sql: &str, dialect: &str) -> Result<Vec<(String, String)>, InspectError> {
+    let statements = parse(sql, dialect_from_str(dialect))?;
+    let mut edges = HashSet::new();
+    for statement in &statements {
+        let mut visitor = V::default();
+        let _ = statement.visit(&mut visitor);
+        let is_cte = |table: &str| visitor.ctes.contains(table);
+        for (left, right) in &visitor.joins {
+            let left = resolve_table(&visitor.aliases, left);
+            let right = resolve_table(&visitor.aliases, right);
+            if left != right && !is_cte(left) && !is_cte(right) {
+                let (left, right) = (left.min(right), left.max(right));
+                edges.insert((left.to_string(), right.to_string()));
+            }
+        }
+        let writes = matches!(
+            visitor.query_type,
+            QueryType::INSERT
+                | QueryType::UPDATE
+                | QueryType::MERGE
+                | QueryType::CREATE_VIEW
+                | QueryType::CREATE_TABLE
+        );
+        if writes {
+            for table in &visitor.tables {
+                if *table != visitor.target_table && !is_cte(table) {
+                    edges.insert((table.clone(), visitor.target_table.clone()));
+                }
+            }
+        }
+    }
+    let mut edges = Vec::from_iter(edges);
+    edges.sort();
+    Ok(edges)
+}
+
+// Like `inspect`, but returns one result for each statement in the input
+pub fn inspect_all(sql: &str) -> Result<Vec<ExtractResult>, InspectError> {
+    inspect_all_with(sql, &InspectOptions::default())
+}
+
+pub fn inspect_all_with(
+    sql: &str,
+    options: &InspectOptions,
+) -> Result<Vec<ExtractResult>, InspectError> {
+    inspect_statements(sql, options, Statements::All, None)
+}
+
+fn check_supported(visitor: &V, options: &InspectOptions) -> Result<(), InspectError> {
+    match &visitor.unsupported {
+        Some(kind) if options.strict_unsupported => Err(InspectError::Unsupported(kind.clone())),
+        _ => Ok(()),
+    }
+}
+
+// Replaces the projected `*` with the `table.column` names of the schema.
+// When a table is unknown, the `WildcardPolicy` applies.
+fn expand_wildcards(visitor: &mut V, options: &InspectOptions) -> Result<(), InspectError> {
+    if !options.expand_wildcards {
+        return Ok(());
+    }
+    expand_wildcards_with(visitor, &options.schema, options.wildcard_policy)
+}
+
+// Like `expand_wildcards`, looking the columns up in `schema`. Only the tables
+// of the projected wildcards are looked up.
+fn expand_wildcards_with(
+    visitor: &mut V,
+    schema: &dyn SchemaProvider,
+    policy: WildcardPolicy,
+) -> Result<(), InspectError> {
+    if visitor.wildcard_sources.is_empty() {
+        return Ok(());
+    }
+    let mut keep_star = false;
+    for table in visitor.wildcard_sources.concat() {
+        match schema.columns_of(&table) {
+            Some(columns) => {
+                for column in columns {
+                    visitor.columns.insert(format!("{table}.{column}"));
+                }
+            }
+            None => match policy {
+                WildcardPolicy::KeepStar => {
+                    keep_star = true;
+                    visitor.warnings.insert(Warning::UnexpandedWildcard(table));
+                }
+                WildcardPolicy::Error => return Err(InspectError::UnexpandedWildcard(table)),
+                WildcardPolicy::Drop => {}
+            },
+        }
+    }
+    if !keep_star {
+        visitor.columns.shift_remove("*");
+    }
+    Ok(())
+}
+
+// Replaces the alias prefix of a fully-qualified column with the real table name.
+// The prefix is everything before the column name, so the schema-qualified
+// columns (`public.orders.id`) are left as they are.
+fn resolve_alias(aliases: &HashMap<String, String>, column: &str) -> String {
+    let Some((prefix, col)) = column.rsplit_once('.') else {
+        return column.to_string();
+    };
+    match aliases.get(prefix) {
+        Some(_) => format!("{}.{}", resolve_table(aliases, prefix), col),
+        None => column.to_string(),
+    }
+}
+
+// Returns the table an alias refers to, following the aliases of aliases
+// (`FROM u AS x` in a subquery of `FROM users u`). On a cycle, the resolution
+// stops at the first alias seen twice.
+fn resolve_table<'a>(aliases: &'a HashMap<String, String>, name: &'a str) -> &'a str {
+    let mut name = name;
+    let mut seen = HashSet::new();
+    while let Some(table) = aliases.get(name) {
+        if !seen.insert(name) {
+            break;
+        }
+        name = table;
+    }
+    name
+}
+
+// Dedupes the names ignoring the case, keeping the variant seen first (the
+// names not in `seen` come last, in order) at the position of the first variant
+fn dedup_case_insensitive(names: Vec<String>, seen: &[String]) -> Vec<String> {
+    let rank = |name: &String| seen.iter().position(|s| s == name).unwrap_or(usize::MAX);
+    let mut kept: Vec<String> = vec![];
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for name in names {
+        match positions.get(&name.to_lowercase()) {
+            Some(&position) => {
+                if rank(&name) < rank(&kept[position]) {
+                    kept[position] = name;
+                }
+            }
+            None => {
+                positions.insert(name.to_lowercase(), kept.len());
+                kept.push(name);
+            }
+        }
+    }
+    kept
+}
+
+// Dedupes the names keeping the first occurrence, in order
+fn dedup_ordered(names: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+}
+
+fn extract_result(mut visitor: V) -> ExtractResult {
+    // The aliases of aliases map to the real table too
+    let alias_map = visitor
+        .aliases
+        .keys()
+        .map(|alias| {
+            (
+                alias.clone(),
+                resolve_table(&visitor.aliases, alias).to_string(),
+            )
+        })
+        .collect::<BTreeMap<String, String>>();
+    if visitor.keep_aliases {
+        visitor.aliases.clear();
+    }
+    let mut columns: Vec<String> = Vec::from_iter(visitor.columns.iter().map(|c| c.to_string()));
+    // We replace the aliases with the real table name for
+    // the fully-qualified columns
+    for c in columns.iter_mut() {
+        *c = resolve_alias(&visitor.aliases, c);
+    }
+
+    let mut seen = HashSet::new();
+    let mut duplicate_projections: Vec<String> = visitor
+        .projection
+        .iter()
+        .map(|c| resolve_alias(&visitor.aliases, c))
+        .filter(|c| !seen.insert(c.clone()))
+        .collect();
+    duplicate_projections.sort();
+    duplicate_projections.dedup();
+
+    // The CTE names are not tables
+    let mut tables: Vec<String> = Vec::from_iter(
+        visitor
+            .tables
+            .iter()
+            .filter(|t| !visitor.ctes.contains(*t))
+            .filter(|t| !(visitor.base_tables_only && visitor.table_functions.contains(*t)))
+            .map(|t| t.to_string()),
+    );
+    // The locked tables can be referenced by alias
+    let mut locked_tables: Vec<String> = Vec::from_iter(
+        visitor
+            .locked_tables
+            .iter()
+            .map(|t| resolve_table(&visitor.aliases, t).to_string()),
+    );
+    if visitor.ordered {
+        // An alias and its table can refer to the same column
+        dedup_ordered(&mut columns);
+    } else {
+        columns.sort();
+        columns.dedup();
+        tables.sort();
+    }
+    if visitor.case_insensitive_dedup {
+        let seen: Vec<String> = visitor
+            .seen
+            .iter()
+            .map(|name| resolve_alias(&visitor.aliases, name))
+            .collect();
+        tables = dedup_case_insensitive(tables, &seen);
+        columns = dedup_case_insensitive(columns, &seen);
+        if !visitor.ordered {
+            tables.sort();
+            columns.sort();
+        }
+    }
+    locked_tables.sort();
+    locked_tables.dedup();
+    let mut databases: Vec<String> = Vec::from_iter(visitor.databases);
+    databases.sort();
+    let cross_database = databases.len() > 1;
+    let mut predicate_values: Vec<(String, String)> = visitor
+        .predicate_values
+        .iter()
+        .map(|(column, value)| (resolve_alias(&visitor.aliases, column), value.clone()))
+        .collect();
+    predicate_values.sort();
+    predicate_values.dedup();
+    let mut sequences: Vec<String> = Vec::from_iter(visitor.sequences);
+    sequences.sort();
+    let blast_radius = visitor.blast_radius.unwrap_or(match tables.len() {
+        0 | 1 => BlastRadius::FullTable,
+        _ => BlastRadius::MultiTable,
+    });
+    let mut group_by: Vec<String> = visitor
+        .group_by
+        .iter()
+        .map(|expr| match column_name(expr) {
+            Some(column) => resolve_alias(&visitor.aliases, &column),
+            None => expr.to_string(),
+        })
+        .collect();
+    group_by.sort();
+    group_by.dedup();
+    let mut filter_columns: Vec<String> = visitor
+        .filter_columns
+        .iter()
+        .map(|c| resolve_alias(&visitor.aliases, c))
+        .collect();
+    filter_columns.sort();
+    filter_columns.dedup();
+    let mut having_columns: Vec<String> = visitor
+        .having_columns
+        .iter()
+        .map(|c| resolve_alias(&visitor.aliases, c))
+        .collect();
+    having_columns.sort();
+    having_columns.dedup();
+    let lineage: Vec<(String, String)> = visitor
+        .lineage
+        .iter()
+        .map(|(target, source)| (target.clone(), resolve_alias(&visitor.aliases, source)))
+        .collect();
+    let distinct_on: Vec<String> = visitor
+        .distinct_on
+        .iter()
+        .map(|expr| match column_name(expr) {
+            Some(column) => resolve_alias(&visitor.aliases, &column),
+            None => expr.to_string(),
+        })
+        .collect();
+    let order_by: Vec<OrderByColumn> = visitor
+        .order_by
+        .iter()
+        .map(|(expr, asc)| match (column_name(expr), literal_u64(expr)) {
+            (Some(column), _) => OrderByColumn {
+                column: resolve_alias(&visitor.aliases, &column),
+                asc: *asc,
+                ordinal: false,
+            },
+            (None, Some(position)) => OrderByColumn {
+                column: position.to_string(),
+                asc: *asc,
+                ordinal: true,
+            },
+            (None, None) => OrderByColumn {
+                column: expr.to_string(),
+                asc: *asc,
+                ordinal: false,
+            },
+        })
+        .collect();
+    let joins: Vec<JoinInfo> = visitor
+        .join_infos
+        .iter()
+        .map(|join| {
+            let table = |name: &String| resolve_table(&visitor.aliases, name).to_string();
+            let mut columns: Vec<String> = join
+                .columns
+                .iter()
+                .map(|c| resolve_alias(&visitor.aliases, c))
+                .collect();
+            columns.sort();
+            columns.dedup();
+            JoinInfo {
+                left_table: table(&join.left_table),
+                right_table: table(&join.right_table),
+                join_type: join.join_type,
+                columns,
+            }
+        })
+        .collect();
+    let mut json_tables: Vec<JsonTable> = visitor
+        .json_tables
+        .iter()
+        .map(|json_table| JsonTable {
+            source: resolve_alias(&visitor.aliases, &json_table.source),
+            ..json_table.clone()
+        })
+        .collect();
+    json_tables.sort();
+    let mut correlated_columns: Vec<String> = visitor
+        .correlated_columns
+        .iter()
+        .map(|c| resolve_alias(&visitor.aliases, c))
+        .collect();
+    correlated_columns.sort();
+    let mut functions: Vec<String> = Vec::from_iter(visitor.functions);
+    functions.sort();
+    let mut window_functions: Vec<String> = Vec::from_iter(visitor.window_functions);
+    window_functions.sort();
+    // The unqualified columns of a single table scope belong to that table
+    let unresolved_columns: Vec<String> = columns
+        .iter()
+        .filter(|c| !c.contains('.') && visitor.unresolved.contains(*c))
+        .cloned()
+        .collect();
+    let mut schemas: Vec<String> = Vec::from_iter(visitor.schemas);
+    schemas.sort();
+    let complexity_score = (2 * joins.len()
+        + visitor.predicate_count
+        + 3 * visitor.max_depth
+        + 2 * visitor.subquery_count
+        + visitor.function_calls) as u32;
+    let mut ctes: Vec<String> = Vec::from_iter(visitor.ctes);
+    ctes.sort();
+    let mut derived_tables: Vec<String> = Vec::from_iter(visitor.derived_tables);
+    derived_tables.sort();
+    let mut cte_dependencies: Vec<(String, String)> = Vec::from_iter(visitor.cte_dependencies);
+    cte_dependencies.sort();
+    let mut warnings: Vec<Warning> = Vec::from_iter(visitor.warnings);
+    warnings.sort();
+    let mut procedures: Vec<String> = Vec::from_iter(visitor.procedures);
+    procedures.sort();
+    let target_table = visitor.target_table.clone();
+    let query_type = visitor.query_type;
+    let access = Access::of(
+        &query_type,
+        visitor.locking.is_some() || visitor.select_into,
+    );
+    ExtractResult {
+        columns,
+        tables,
+        target_table,
+        query_type,
+        source: None,
+        locking: visitor.locking,
+        locked_tables,
+        databases,
+        cross_database,
+        materialized: visitor.materialized,
+        limit: visitor.limit,
+        offset: visitor.offset,
+        has_limit: visitor.has_limit,
+        duplicate_projections,
+        overwrite: visitor.overwrite,
+        has_upsert: visitor.has_upsert,
+        idempotent: visitor.idempotent,
+        access,
+        locks: visitor.locks,
+        max_depth: visitor.max_depth,
+        procedures,
+        predicate_values,
+        predicate_count: visitor.predicate_count,
+        sequences,
+        warnings,
+        blast_radius,
+        invalid_aggregation: visitor.invalid_aggregation,
+        column_types: visitor.column_types,
+        alias_map,
+        group_by,
+        ctes,
+        cte_dependencies,
+        order_by,
+        joins,
+        json_tables,
+        target_schema: visitor.target_schema,
+        correlated_columns,
+        functions,
+        window_functions,
+        returning: visitor.returning,
+        resolved_targets: visitor.resolved_targets,
+        unresolved_columns,
+        schemas,
+        complexity_score,
+        distinct: visitor.distinct,
+        distinct_on,
+        filter_columns,
+        having_columns,
+        derived_tables,
+        lineage,
+        object_type: visitor.object_type,
+        parameters: visitor.parameters,
+    }
+}
+
+// Token locations are (line, column) pairs counted in chars: returns the function
+// mapping them back to byte offsets in the input
+fn byte_offset(sql: &str) -> impl Fn(Location) -> usize + '_ {
+    let mut line_starts = vec![0];
+    line_starts.extend(sql.match_indices('\n').map(|(i, _)| i + 1));
+    move |location: Location| {
+        let Some(&start) = line_starts.get(location.line.saturating_sub(1) as usize) else {
+            return sql.len();
+        };
+        sql[start..]
+            .char_indices()
+            .nth(location.column.saturating_sub(1) as usize)
+            .map_or(sql.len(), |(i, _)| start + i)
+    }
+}
+
+// Splits the tokenized input on the top-level semicolons and returns the
+// text of every statement, trimmed of the surrounding whitespace and comments.
+// The semicolons of the `BEGIN ... END` blocks (e.g. a trigger body) and of the
+// `CASE ... END` statements are not top-level. A `BEGIN` starting a statement
+// starts a transaction, and `END IF`, `END LOOP`, ... close blocks not counted.
+fn statement_sources(sql: &str, tokens: &[TokenWithSpan]) -> Vec<String> {
+    let offset = byte_offset(sql);
+    let words: Vec<&Token> = tokens
+        .iter()
+        .map(|t| &t.token)
+        .filter(|t| !matches!(t, Token::Whitespace(_)))
+        .collect();
+    let is_word = |i: usize, word: &str| matches!(words.get(i), Some(Token::Word(w)) if w.value.eq_ignore_ascii_case(word));
+    let mut sources = vec![];
+    let mut current: Option<(usize, usize)> = None;
+    let mut depth = 0usize;
+    let mut i = 0;
+    for token in tokens {
+        if !matches!(token.token, Token::Whitespace(_)) {
+            if (is_word(i, "BEGIN") && current.is_some()) || is_word(i, "CASE") {
+                depth += 1;
+            } else if is_word(i, "END")
+                && !["IF", "LOOP", "WHILE", "REPEAT"]
+                    .iter()
+                    .any(|word| is_word(i + 1, word))
+            {
+                depth = depth.saturating_sub(1);
+            }
+            i += 1;
+        }
+        match &token.token {
+            Token::SemiColon if depth > 0 => {
+                current = current.map(|(start, _)| (start, offset(token.span.end)));
+            }
+            Token::SemiColon | Token::EOF => {
+                if let Some((start, end)) = current.take() {
+                    sources.push(sql[start..end].to_string());
+                }
+            }
+            Token::Whitespace(_) => {}
+            _ => {
+                let end = offset(token.span.end);
+                let start = current.map_or_else(|| offset(token.span.start), |(start, _)| start);
+                current = Some((start, end));
+            }
+        }
+    }
+    if let Some((start, end)) = current {
+        sources.push(sql[start..end].to_string());
+    }
+    sources
+}
+
+// True when the two results touch the same tables and columns, with the same
+// query type, whatever the order
+pub fn same_objects(a: &ExtractResult, b: &ExtractResult) -> bool {
+    let set = |names: &[String]| names.iter().cloned().collect::<HashSet<String>>();
+    a.query_type == b.query_type
+        && set(&a.tables) == set(&b.tables)
+        && set(&a.columns) == set(&b.columns)
+}
+
+// Best-effort fallback for the SQL that `inspect` fails to parse: tokenizes
+// the input (up to the first tokenizer error) and collects the names following
+// FROM, JOIN, INTO and UPDATE, plus the comma-separated FROM lists.
+// The result is approximate: CTE names and table functions are reported as
+// tables, and tables referenced in any other way are missed.
+pub fn scan_tables_lossy(sql: &str) -> Vec<String> {
+    let dialect = GenericDialect {};
+    let tokens = match Tokenizer::new(&dialect, sql).tokenize() {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            // The error location is a (line, column) pair counted in chars
+            let line_start = match err.location.line {
+                0 | 1 => 0,
+                line => sql
+                    .match_indices('\n')
+                    .nth(line as usize - 2)
+                    .map_or(sql.len(), |(i, _)| i + 1),
+            };
+            let end = sql[line_start..]
+                .char_indices()
+                .nth((err.location.column as usize).saturating_sub(1))
+                .map_or(sql.len(), |(i, _)| line_start + i);
+            Tokenizer::new(&dialect, &sql[..end])
+                .tokenize()
+                .unwrap_or_default()
+        }
+    };
+    let tokens: Vec<Token> = tokens
+        .into_iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .collect();
+    let is_keyword = |token: Option<&Token>, keywords: &[Keyword]| matches!(token, Some(Token::Word(word)) if word.quote_style.is_none() && keywords.contains(&word.keyword));
+
+    let mut tables = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        let starts_table = match &tokens[i] {
+            Token::Word(word) if word.quote_style.is_none() => match word.keyword {
+                Keyword::FROM | Keyword::JOIN | Keyword::INTO => true,
+                // Not a `FOR UPDATE` lock nor an `ON DUPLICATE KEY UPDATE`
+                Keyword::UPDATE => {
+                    i == 0 || !is_keyword(tokens.get(i - 1), &[Keyword::FOR, Keyword::KEY])
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        i += 1;
+        if !starts_table {
+            continue;
+        }
+        loop {
+            if is_keyword(tokens.get(i), &[Keyword::ONLY]) {
+                i += 1;
+            }
+            // A subquery, LATERAL or VALUES are not tables
+            if is_keyword(
+                tokens.get(i),
+                &[Keyword::SELECT, Keyword::LATERAL, Keyword::VALUES],
+            ) {
+                break;
+            }
+            let mut parts = vec![];
+            while let Some(Token::Word(part)) = tokens.get(i) {
+                parts.push(part.value.clone());
+                i += 1;
+                if tokens.get(i) != Some(&Token::Period) {
+                    break;
+                }
+                i += 1;
+            }
+            if parts.is_empty() {
+                break;
+            }
+            tables.push(parts.join("."));
+            // Skip the alias, then keep going if a list of tables follows
+            if is_keyword(tokens.get(i), &[Keyword::AS]) {
+                i += 1;
+            }
+            if matches!(tokens.get(i), Some(Token::Word(word)) if word.quote_style.is_some() || word.keyword == Keyword::NoKeyword)
+            {
+                i += 1;
+            }
+            if tokens.get(i) != Some(&Token::Comma) {
+                break;
+            }
+            i += 1;
+        }
+    }
+    tables.sort();
+    tables.dedup();
+    tables
+}
+
+// This is the entry point for the WASM module, return the result as a JS object
+#[wasm_bindgen(skip_typescript)]
+pub fn sqlinspector(sql: &str, dialect: Option<String>, options: JsValue) -> JsValue {
+    let res = inspect(sql, dialect.as_deref().unwrap_or("generic"));
+    to_js(
+        &InspectResponse::from(res),
+        &OutputOptions::from_js(options),
+    )
+}
+
+// The options of the objects returned to JS, e.g. `{ camelCase: true }`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct OutputOptions {
+    // Use camelCase keys (`targetTable`) instead of the snake_case ones
+    camel_case: bool,
+}
+
+impl OutputOptions {
+    // A missing or invalid options object falls back to the defaults
+    fn from_js(options: JsValue) -> Self {
+        serde_wasm_bindgen::from_value(options).unwrap_or_default()
+    }
+}
+
+// A value that can't be converted (e.g. a number above `Number.MAX_SAFE_INTEGER`)
+// is returned as an `{ error: { message } }` object
+fn to_js<T: Serialize>(value: &T, options: &OutputOptions) -> JsValue {
+    let value = serde_wasm_bindgen::to_value(value).unwrap_or_else(|err| {
+        let error = InspectResponse::Error {
+            error: ErrorResponse {
+                message: err.to_string(),
+                near: None,
+            },
+        };
+        serde_wasm_bindgen::to_value(&error).unwrap_or(JsValue::NULL)
+    });
+    match options.camel_case {
+        true => camel_case_keys(&value),
+        false => value,
+    }
+}
+
+// Copies the value, renaming the keys of the plain objects to camelCase.
+// The keys of the maps are data (e.g. the aliases of `alias_map`), so they are kept.
+fn camel_case_keys(value: &JsValue) -> JsValue {
+    if Array::is_array(value) {
+        Array::from(value)
+            .iter()
+            .map(|item| camel_case_keys(&item))
+            .collect::<Array>()
+            .into()
+    } else if value.is_object() && !value.is_instance_of::<Map>() {
+        let object = Object::new();
+        for entry in Object::entries(value.unchecked_ref()).iter() {
+            let entry = Array::from(&entry);
+            let key = entry.get(0).as_string().unwrap_or_default();
+            let _ = Reflect::set(
+                &object,
+                &camel_case(&key).into(),
+                &camel_case_keys(&entry.get(1)),
+            );
+        }
+        object.into()
+    } else {
+        value.clone()
+    }
+}
+
+// `target_table` -> `targetTable`
+fn camel_case(key: &str) -> String {
+    let mut parts = key.split('_');
+    let mut name = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            name.extend(first.to_uppercase());
+            name.push_str(chars.as_str());
+        }
+    }
+    name
+}
+
+// The outcome of inspecting a query, as returned to JS:
+// either the result or an `{ error: { message, near } }` object
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum InspectResponse {
+    Result(Box<ExtractResult>),
+    Error { error: ErrorResponse },
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    message: String,
+    // The SQL snippet where the parser failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    near: Option<String>,
+}
+
+impl From<Result<ExtractResult, InspectError>> for InspectResponse {
+    fn from(res: Result<ExtractResult, InspectError>) -> Self {
+        match res {
+            Ok(mut res) => {
+                // JS numbers can't hold the larger row counts, e.g. the MySQL
+                // `LIMIT 5, 18446744073709551615` (all the rows after the 5th),
+                // nor the larger positions of `ORDER BY 9007199254740993`
+                let max = Number::MAX_SAFE_INTEGER as u64;
+                res.limit = res.limit.map(|limit| limit.min(max));
+                res.offset = res.offset.map(|offset| offset.min(max));
+                for warning in &mut res.warnings {
+                    if let Warning::UnresolvedPosition(position) = warning {
+                        *position = (*position).min(max);
+                    }
+                }
+                InspectResponse::Result(Box::new(res))
+            }
+            Err(err) => InspectResponse::Error {
+                error: ErrorResponse {
+                    message: err.to_string(),
+                    near: match err {
+                        InspectError::Parse { snippet, .. } => Some(snippet),
+                        _ => None,
+                    },
+                },
+            },
+        }
+    }
+}
+
+// Like `sqlinspector`, but returns an `{ error: { message } }` object when the input
+// can't be parsed or has more than one statement
+#[wasm_bindgen(skip_typescript)]
+pub fn sqlinspector_single(sql: &str, dialect: Option<String>, options: JsValue) -> JsValue {
+    let inspect_options = InspectOptions {
+        dialect: dialect_from_str(dialect.as_deref().unwrap_or("generic")),
+        ..Default::default()
+    };
+    to_js(
+        &InspectResponse::from(inspect_single_with(sql, &inspect_options)),
+        &OutputOptions::from_js(options),
+    )
+}
+
+fn inspect_batch(sqls: &[String], dialect: &str) -> Vec<InspectResponse> {
+    let options = InspectOptions {
+        dialect: dialect_from_str(dialect),
+        ..Default::default()
+    };
+    sqls.iter()
+        .map(|sql| InspectResponse::from(inspect_with(sql, &options)))
+        .collect()
+}
+
+// Inspects many queries with a single call, to avoid crossing the JS/WASM
+// boundary for each of them. Returns an array with a result (or an error) per query.
+#[wasm_bindgen(skip_typescript)]
+#[allow(clippy::boxed_local)] // wasm-bindgen takes JS arrays as boxed slices
+pub fn sqlinspector_batch(
+    sqls: Box<[JsString]>,
+    dialect: Option<String>,
+    options: JsValue,
+) -> JsValue {
+    let sqls: Vec<String> = sqls.iter().map(String::from).collect();
+    let dialect = dialect.as_deref().unwrap_or("generic");
+    to_js(
+        &inspect_batch(&sqls, dialect),
+        &OutputOptions::from_js(options),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_extract(sql: &str, columns: Vec<&str>, tables: Vec<&str>, query_type: QueryType) {
+        let res = inspect(sql, "generic").unwrap();
+        assert_eq!(res.columns, columns);
+        assert_eq!(res.tables, tables);
+        assert_eq!(res.query_type, query_type);
+    }
+
+    #[test]
+    fn select() {
+        let tests = vec![
+            (
+                // simple
+                "SELECT id FROM users WHERE age > 30",
                 vec!["age", "id"],
                 vec!["users"],
             ),(
-                // alias (must be ignored)
-                "SELECT id, name as name2 FROM users WHERE age > 30",
-                vec!["age", "id", "name"],
-                vec!["users"],
+                // alias (must be ignored)
+                "SELECT id, name as name2 FROM users WHERE age > 30",
+                vec!["age", "id", "name"],
+                vec!["users"],
+            ),(
+                // wildcard
+                "SELECT * FROM users WHERE age > 30",
+                vec!["*", "age"],
+                vec!["users"],
+            ),(
+                // qualified wildcard
+                "SELECT users.* FROM users WHERE age > 30",
+                vec!["age", "users.*"],
+                vec!["users"],
+            ),(
+                // qualified wildcards, with aliases
+                "SELECT u.*, o.* FROM users u JOIN orders o ON o.user_id = u.id",
+                vec!["orders.*", "orders.user_id", "users.*", "users.id"],
+                vec!["orders", "users"],
+            ),(
+                // qualified wildcard, in a subquery
+                "SELECT id FROM (SELECT u.* FROM users u) AS s",
+                vec!["id", "users.*"],
+                vec!["users"],
+            ),(
+                // compound identifier
+                "SELECT users.id, users.name FROM users WHERE age > 30",
+                vec!["age", "users.id", "users.name"],
+                vec!["users"],
+            ),(
+                // join with no fully qualified columns
+                "select address, name from table1 join table2 on table1.id = table2.id",
+                vec!["address", "name", "table1.id", "table2.id"],
+                vec!["table1", "table2"]
+            ),(
+                // Join
+                "SELECT users.id, users.name, orders.id FROM users JOIN orders ON users.id = orders.user_id WHERE age > 30",
+                vec!["age", "orders.id", "orders.user_id", "users.id", "users.name"],
+                vec!["orders", "users"]
+            ),(
+                // join with some not compund identifiers
+                "SELECT users.id, name, orders.id, title FROM users JOIN orders ON users.id = orders.user_id WHERE age > 30",
+                vec!["age", "name", "orders.id", "orders.user_id", "title", "users.id"],
+                vec!["orders", "users"]
+            ), (
+                // join with some alias
+                "SELECT id, users2.name as name2, orders.id, title FROM users as users2 JOIN orders ON users.id = orders.user_id WHERE age > 30",
+                vec!["age", "id", "orders.id", "orders.user_id", "title", "users.id", "users.name"],
+                vec!["orders", "users"]
+            ), (
+                // more aliases
+                "Select t1.test_date, t3.testno, t1.examno, t1.serialno, type, (t2.started - t2.ended) as hours
+                 From Table1 t1, Table2 t2, Table3 t3
+                     Where t1.testno = t2.testno
+                     And t3.testno = 1
+                     and type = 'xxxxx'; ",
+                vec![
+                    "Table1.examno", 
+                    "Table1.serialno", 
+                    "Table1.test_date", 
+                    "Table1.testno",
+                    "Table2.ended", 
+                    "Table2.started", 
+                    "Table2.testno", 
+                    "Table3.testno", 
+                    "type",
+                ],
+                vec!["Table1", "Table2", "Table3"]
+            ), (
+                // multiple joins
+                "SELECT customerName, customercity, customermail, ordertotal, salestotal
+                FROM table1 AS t1
+                INNER JOIN table2 AS t2 ON t1.customerid = t2.customerid
+                LEFT JOIN
+                    table3 AS t3
+                    ON t2.orderId = t3.orderId
+                    WHERE t3.salesId IS NULL",
+                vec![
+                    "customerName", 
+                    "customercity", 
+                    "customermail", 
+                    "ordertotal", 
+                    "salestotal",
+                    "table1.customerid", 
+                    "table2.customerid",
+                    "table2.orderId",
+                    "table3.orderId", 
+                    "table3.salesId"
+                ],
+                vec!["table1", "table2", "table3"]
+            ),(
+                // complex query with join, counts and group by 
+                "SELECT
+                    t1.id, 
+                    t1.label_real_address, 
+                    t1.ext, 
+                    COUNT(t2.contact_id), 
+                    COUNT(t4.release_id) 
+                FROM
+                    table1 t1
+                    LEFT JOIN table2 t2  ON t2.contact_type='lx' AND t2.contact_id=t1.id 
+                    LEFT JOIN table3 t3 ON t3.id=t1.id 
+                    LEFT JOIN table4 t4 ON t3.release_id=t4.release_id 
+                GROUP BY t1.label_real_address 
+                ORDER BY COUNT(t2.contact_id) DESC", 
+                vec![
+                    "table1.ext", 
+                    "table1.id", 
+                    "table1.label_real_address",
+                    "table2.contact_id", 
+                    "table2.contact_type",
+                    "table3.id", 
+                    "table3.release_id",
+                    "table4.release_id", 
+                ],
+                vec!["table1", "table2", "table3", "table4"]
+            ),(
+                "SELECT id, name from (SELECT * FROM users UNION SELECT * FROM customers)",
+                vec!["id", "name"],
+                vec!["customers", "users"]
             ),(
-                // wildcard
-                "SELECT * FROM users WHERE age > 30",
-                vec!["*", "age"],
-                vec!["users"],
+                // ordered aggregate
+                "SELECT STRING_AGG(name, ',' ORDER BY created_at) FROM users",
+                vec!["created_at", "name"],
+                vec!["users"]
             ),(
-                // compound identifier
-                "SELECT users.id, users.name FROM users WHERE age > 30",
-                vec!["age", "users.id", "users.name"],
-                vec!["users"],
+                // ordered aggregate with alias
+                "SELECT u.id, ARRAY_AGG(u.name ORDER BY u.created_at DESC) FROM users u GROUP BY u.id",
+                vec!["users.created_at", "users.id", "users.name"],
+                vec!["users"]
             ),(
-                // join with no fully qualified columns
-                "select address, name from table1 join table2 on table1.id = table2.id",
-                vec!["address", "name", "table1.id", "table2.id"],
-                vec!["table1", "table2"]
+                // row constructor IN a VALUES list
+                "SELECT id FROM t WHERE (a, b) IN (VALUES (1, 2), (3, 4))",
+                vec!["a", "b", "id"],
+                vec!["t"]
             ),(
-                // Join
-                "SELECT users.id, users.name, orders.id FROM users JOIN orders ON users.id = orders.user_id WHERE age > 30",
-                vec!["age", "orders.id", "orders.user_id", "users.id", "users.name"],
-                vec!["orders", "users"]
+                // row constructor with an alias
+                "SELECT x.id FROM t x WHERE (x.a, x.b) IN (VALUES (1, 2), (3, 4))",
+                vec!["t.a", "t.b", "t.id"],
+                vec!["t"]
             ),(
-                // join with some not compund identifiers
-                "SELECT users.id, name, orders.id, title FROM users JOIN orders ON users.id = orders.user_id WHERE age > 30",
-                vec!["age", "name", "orders.id", "orders.user_id", "title", "users.id"],
+                // special-syntax string functions
+                "SELECT TRIM(BOTH ' ' FROM name), TRIM(LEADING prefix FROM code), SUBSTRING(title FROM 1 FOR 3) FROM users",
+                vec!["code", "name", "prefix", "title"],
+                vec!["users"]
+            ),(
+                // special-syntax string functions with an alias
+                "SELECT TRIM(u.name), SUBSTRING(u.code, 1, 3) FROM users u WHERE SUBSTRING(u.title FROM 2) = 'x'",
+                vec!["users.code", "users.name", "users.title"],
+                vec!["users"]
+            ),(
+                // OVERLAY
+                "SELECT OVERLAY(name PLACING prefix FROM 2 FOR len) FROM users",
+                vec!["len", "name", "prefix"],
+                vec!["users"]
+            ),(
+                // POSITION
+                "SELECT id FROM users u WHERE POSITION(needle IN u.name) > 0",
+                vec!["id", "needle", "users.name"],
+                vec!["users"]
+            ),(
+                // IS [NOT] NULL
+                "SELECT u.id FROM users u WHERE deleted_at IS NULL AND u.email IS NOT NULL",
+                vec!["deleted_at", "users.email", "users.id"],
+                vec!["users"]
+            ),(
+                // COALESCE / NULLIF
+                "SELECT COALESCE(a, b, c), NULLIF(x, y) FROM t",
+                vec!["a", "b", "c", "x", "y"],
+                vec!["t"]
+            ),(
+                // GREATEST / LEAST with an alias
+                "SELECT GREATEST(o.p, o.q) FROM orders o WHERE LEAST(o.r, s) > 0",
+                vec!["orders.p", "orders.q", "orders.r", "s"],
+                vec!["orders"]
+            ),(
+                // zero-argument window function, the columns are in OVER
+                "SELECT id, ROW_NUMBER() OVER (PARTITION BY dept ORDER BY salary DESC) FROM emp",
+                vec!["dept", "id", "salary"],
+                vec!["emp"]
+            ),(
+                // zero-argument window function with an alias
+                "SELECT e.id, RANK() OVER (PARTITION BY e.dept ORDER BY e.salary) FROM emp e",
+                vec!["emp.dept", "emp.id", "emp.salary"],
+                vec!["emp"]
+            ),(
+                // AT TIME ZONE
+                "SELECT created_at AT TIME ZONE 'UTC' FROM events WHERE events.at AT TIME ZONE tz > now()",
+                vec!["created_at", "events.at", "tz"],
+                vec!["events"]
+            ),(
+                // date-part functions with an alias
+                "SELECT DATE_TRUNC('day', e.created_at), EXTRACT(YEAR FROM e.updated_at), DATE_PART('hour', ts) FROM events e",
+                vec!["events.created_at", "events.updated_at", "ts"],
+                vec!["events"]
+            ),(
+                // boolean expression in the projection
+                "SELECT (a > 0) AND (b < 10) AS flag FROM t",
+                vec!["a", "b"],
+                vec!["t"]
+            ),(
+                // boolean expression in the projection with an alias
+                "SELECT x.a = 1 OR NOT x.c, id FROM t x",
+                vec!["id", "t.a", "t.c"],
+                vec!["t"]
+            ),(
+                // named window
+                "SELECT rank() OVER w FROM t WINDOW w AS (PARTITION BY dept ORDER BY salary)",
+                vec!["dept", "salary"],
+                vec!["t"]
+            ),(
+                // named windows with an alias, one based on the other
+                "SELECT e.id, SUM(e.pay) OVER w2 FROM emp e WINDOW w1 AS (PARTITION BY e.dept), w2 AS (w1 ORDER BY e.hired)",
+                vec!["emp.dept", "emp.hired", "emp.id", "emp.pay"],
+                vec!["emp"]
+            ),(
+                // HAVING with a subquery
+                "SELECT dept, SUM(amount) FROM t1 GROUP BY dept HAVING SUM(amount) > (SELECT AVG(t2.amount) FROM t2)",
+                vec!["amount", "dept", "t2.amount"],
+                vec!["t1", "t2"]
+            ),(
+                // HAVING with nested subqueries
+                "SELECT o.dept FROM t1 o GROUP BY o.dept HAVING SUM(o.amount) > (SELECT AVG(a.amount) FROM t2 a WHERE a.x IN (SELECT y FROM t3))",
+                vec!["t1.amount", "t1.dept", "t2.amount", "t2.x", "y"],
+                vec!["t1", "t2", "t3"]
+            ),(
+                // ORDER BY of a union
+                "SELECT a FROM x UNION SELECT b FROM y ORDER BY a",
+                vec!["a", "b"],
+                vec!["x", "y"]
+            ),(
+                // ORDER BY of a union, by the output alias
+                "SELECT x.a AS k FROM x UNION ALL SELECT b FROM y ORDER BY k DESC, c",
+                vec!["b", "c", "x.a"],
+                vec!["x", "y"]
+            ),(
+                // ORDER BY an output alias
+                "SELECT COUNT(*) AS total, dept FROM emp GROUP BY dept ORDER BY total",
+                vec!["dept"],
+                vec!["emp"]
+            ),(
+                // projected LATERAL subquery, correlated to the outer table
+                "SELECT u.id, lateral_sub.val FROM users u, LATERAL (SELECT o.val FROM orders o WHERE o.user_id = u.id ORDER BY o.created_at DESC LIMIT 1) lateral_sub",
+                vec!["lateral_sub.val", "orders.created_at", "orders.user_id", "orders.val", "users.id"],
                 vec!["orders", "users"]
+            ),(
+                // LATERAL join
+                "SELECT u.id, l.total FROM users u CROSS JOIN LATERAL (SELECT SUM(amount) AS total FROM payments p WHERE p.user_id = u.id) l",
+                vec!["amount", "l.total", "payments.user_id", "users.id"],
+                vec!["payments", "users"]
+            ),(
+                // tuple IN a multi-column subquery
+                "SELECT id FROM t WHERE (a, b) IN (SELECT x, y FROM t2)",
+                vec!["a", "b", "id", "x", "y"],
+                vec!["t", "t2"]
+            ),(
+                // tuple IN a multi-column subquery with aliases
+                "SELECT t.id FROM t WHERE (t.a, t.b) NOT IN (SELECT s.x, s.y FROM t2 s WHERE s.z > 0)",
+                vec!["t.a", "t.b", "t.id", "t2.x", "t2.y", "t2.z"],
+                vec!["t", "t2"]
+            ),(
+                // GROUP BY a function of a column
+                "SELECT COUNT(*) FROM orders GROUP BY DATE_TRUNC('month', created_at)",
+                vec!["created_at"],
+                vec!["orders"]
+            ),(
+                // GROUP BY functions of columns with an alias
+                "SELECT COUNT(*) FROM orders o GROUP BY EXTRACT(YEAR FROM o.placed_at), LOWER(o.region)",
+                vec!["orders.placed_at", "orders.region"],
+                vec!["orders"]
+
+        )];
+
+        for (sql, columns, tables) in tests {
+            test_extract(sql, columns, tables, QueryType::SELECT);
+        }
+    }
+
+    #[test]
+    fn insert() {
+        let tests = vec![
+            (
+                // simple
+                "INSERT INTO users (id, name) VALUES (1, 'Marco')",
+                vec!["users.id", "users.name"],
+                vec!["users"],
             ), (
-                // join with some alias
-                "SELECT id, users2.name as name2, orders.id, title FROM users as users2 JOIN orders ON users.id = orders.user_id WHERE age > 30",
-                vec!["age", "id", "orders.id", "orders.user_id", "title", "users.id", "users.name"],
-                vec!["orders", "users"]
+                // multiple
+                "INSERT INTO Customers (CustomerName, ContactName, Address, City, PostalCode, Country)
+                    VALUES
+                    ('Platformatic', 'Luca', 'xxx 21', 'Vancouver', '4006', 'Canada'),
+                    ('Platformatic eu', 'Marco', 'yyy 23', 'Bologna', '40137', 'Italy');",
+                vec!["Customers.Address", "Customers.City", "Customers.ContactName", "Customers.Country", "Customers.CustomerName", "Customers.PostalCode"],
+                vec!["Customers"]
             ), (
-                // more aliases
-                "Select t1.test_date, t3.testno, t1.examno, t1.serialno, type, (t2.started - t2.ended) as hours
-                 From Table1 t1, Table2 t2, Table3 t3
-                     Where t1.testno = t2.testno
-                     And t3.testno = 1
-                     and type = 'xxxxx'; ",
+                // without columns
+                "INSERT INTO Customers VALUES (5,'Harry', 'Potter', 31, 'Hogwarts');",
+                vec![],
+                vec!["Customers"]
+            ),
+                (
+                "INSERT INTO Table1 (test_date, testno, examno, serialno, type, hours)
+                    SELECT T2.test_date, T4.testno, T2.examno, T2.serialno, type, (T3.started- T3.ended) as hours
+                    FROM Table2 T2, Table3 T3, Table4 T4
+                        Where T2.testno = T3.testno
+                        And T4.testno = 1
+                        and type = 'xxxxx'; ",
+                vec![
+                    "Table1.examno",
+                    "Table1.hours",
+                    "Table1.serialno",
+                    "Table1.test_date",
+                    "Table1.testno",
+                    "Table1.type",
+                    "Table2.examno",
+                    "Table2.serialno",
+                    "Table2.test_date",
+                    "Table2.testno",
+                    "Table3.ended",
+                    "Table3.started",
+                    "Table3.testno",
+                    "Table4.testno", 
+                    "type"
+                ],
+                vec!["Table1", "Table2", "Table3", "Table4"],
+            ), (
+                // without columns, from a full-row select
+                "INSERT INTO Customers SELECT * FROM other",
+                vec!["*"],
+                vec!["Customers", "other"]
+            ), (
+                // without columns, from a select with an alias
+                "INSERT INTO Customers SELECT o.name, o.city FROM other o",
+                vec!["other.city", "other.name"],
+                vec!["Customers", "other"]
+            ), (
+                // without columns, from a qualified wildcard
+                "INSERT INTO Customers SELECT o.* FROM other o",
+                vec!["other.*"],
+                vec!["Customers", "other"]
+            )
+        ];
+
+        for (sql, columns, tables) in tests {
+            test_extract(sql, columns, tables, QueryType::INSERT);
+        }
+    }
+
+    #[test]
+    fn delete() {
+        let tests = vec![
+            (
+                // simple
+                "DELETE FROM users WHERE age > 30",
+                vec!["age"],
+                vec!["users"],
+            ),
+            (
+                // With AND condition
+                "DELETE FROM Test
+                    WHERE Testno = 1
+                    AND TYPE = 'xxxxxx';",
+                vec!["TYPE", "Testno"],
+                vec!["Test"],
+            ),
+            (
+                // With EXISTS
+                "DELETE FROM t1
+                    WHERE t1.V1 > t1.V2
+                    AND EXISTS (SELECT * FROM t2 WHERE t2.V1 = t1.V1);",
+                vec!["t1.V1", "t1.V2", "t2.V1"],
+                vec!["t1", "t2"],
+            ),
+            (
+                // soft-deleted rows
+                "DELETE FROM users WHERE deleted_at IS NOT NULL",
+                vec!["deleted_at"],
+                vec!["users"],
+            ),
+        ];
+
+        for (sql, columns, tables) in tests {
+            test_extract(sql, columns, tables, QueryType::DELETE);
+        }
+    }
+
+    #[test]
+    fn update() {
+        let tests = vec![
+            (
+                // simple
+                "UPDATE users SET age = 30",
+                vec!["users.age"],
+                vec!["users"],
+            ),
+            (
+                // With AND condition
+                "UPDATE Test
+                    SET Test_Date = '2021-01-01'
+                    WHERE Testno = 1
+                    AND TYPE = 'xxxxxxx';",
+                vec!["TYPE", "Test.Test_Date", "Testno"],
+                vec!["Test"],
+            ),
+            (
+                // With EXISTS
+                "UPDATE t1
+                    SET t1.V1 = t2.V1
+                    WHERE t1.V1 > t1.V2
+                    AND EXISTS (SELECT * FROM t2 WHERE t2.V1 = t1.V1);",
+                vec!["t1.V1", "t1.V2", "t2.V1"],
+                vec!["t1", "t2"],
+            ),
+            (
+                // Complex update
+                "UPDATE component SET name = p.number
+                       FROM part p 
+                       JOIN
+                           component_part cp ON p.id = cp.partId  JOIN
+                           component c ON cp.componentId = c.id
+                       WHERE p.brandId = 1003
+                       AND component.name='xxx'",
+                vec![
+                    "component.id",
+                    "component.name",
+                    "component_part.componentId",
+                    "component_part.partId",
+                    "part.brandId",
+                    "part.id",
+                    "part.number",
+                ],
+                vec!["component", "component_part", "part"],
+            ),
+        ];
+
+        for (sql, columns, tables) in tests {
+            test_extract(sql, columns, tables, QueryType::UPDATE);
+        }
+    }
+
+    #[test]
+    fn many_statements() {
+        let sql = "SELECT a FROM t1; UPDATE t2 SET b = 1; INSERT INTO t3 (c) VALUES (1);";
+        let res = inspect_all(sql).unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].query_type, QueryType::SELECT);
+        assert_eq!(res[0].tables, vec!["t1"]);
+        assert_eq!(res[0].target_table, "");
+        assert_eq!(res[1].query_type, QueryType::UPDATE);
+        assert_eq!(res[1].tables, vec!["t2"]);
+        assert_eq!(res[1].target_table, "t2");
+        assert_eq!(res[2].query_type, QueryType::INSERT);
+        assert_eq!(res[2].columns, vec!["t3.c"]);
+        assert_eq!(res[2].target_table, "t3");
+
+        // `inspect` returns the result of the first statement
+        let res = inspect(sql, "generic").unwrap();
+        assert_eq!(res.query_type, QueryType::SELECT);
+        assert_eq!(res.tables, vec!["t1"]);
+        assert_eq!(res.columns, vec!["a"]);
+    }
+
+    #[test]
+    fn inspect_all_with_source() {
+        let sql = "SELECT id FROM users;\n  -- the orders\n  UPDATE orders SET total = 0 WHERE id = 'è;é' ;";
+        let options = InspectOptions {
+            include_source: true,
+            ..Default::default()
+        };
+        let res = inspect_all_with(sql, &options).unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].query_type, QueryType::SELECT);
+        assert_eq!(res[0].tables, vec!["users"]);
+        assert_eq!(res[0].source.as_deref(), Some("SELECT id FROM users"));
+        assert_eq!(res[1].query_type, QueryType::UPDATE);
+        assert_eq!(res[1].tables, vec!["orders"]);
+        assert_eq!(
+            res[1].source.as_deref(),
+            Some("UPDATE orders SET total = 0 WHERE id = 'è;é'")
+        );
+
+        // Without the option the source is not populated
+        let res = inspect_all(sql).unwrap();
+        assert!(res.iter().all(|r| r.source.is_none()));
+
+        // The semicolons of a block don't end the statement
+        let sql = "CREATE TRIGGER tr ON orders AFTER INSERT AS BEGIN UPDATE stats SET n = n + 1; DELETE FROM queue; END; SELECT CASE WHEN n > 0 THEN 1 END FROM stats";
+        let options = InspectOptions {
+            dialect: Dialect::MsSql,
+            include_source: true,
+            ..Default::default()
+        };
+        let res = inspect_all_with(sql, &options).unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(
+            res[0].source.as_deref(),
+            Some("CREATE TRIGGER tr ON orders AFTER INSERT AS BEGIN UPDATE stats SET n = n + 1; DELETE FROM queue; END")
+        );
+        assert_eq!(
+            res[1].source.as_deref(),
+            Some("SELECT CASE WHEN n > 0 THEN 1 END FROM stats")
+        );
+        // A transaction
+        let res = inspect_all_with("BEGIN; SELECT 1; COMMIT", &options).unwrap();
+        let sources: Vec<Option<&str>> = res.iter().map(|r| r.source.as_deref()).collect();
+        assert_eq!(
+            sources,
+            vec![Some("BEGIN"), Some("SELECT 1"), Some("COMMIT")]
+        );
+    }
+
+    #[test]
+    fn inspect_all_with_refresh() {
+        let sql = "REFRESH MATERIALIZED VIEW mv; SELECT id FROM users; REFRESH MATERIALIZED VIEW CONCURRENTLY sales.mv2";
+        let options = InspectOptions {
+            dialect: Dialect::Postgres,
+            include_source: true,
+            ..Default::default()
+        };
+        let res = inspect_all_with(sql, &options).unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].query_type, QueryType::REFRESH);
+        assert_eq!(res[0].target_table, "mv");
+        assert_eq!(
+            res[0].source.as_deref(),
+            Some("REFRESH MATERIALIZED VIEW mv")
+        );
+        assert_eq!(res[1].query_type, QueryType::SELECT);
+        assert_eq!(res[1].tables, vec!["users"]);
+        assert_eq!(res[2].target_table, "sales.mv2");
+
+        // The other statements must parse
+        assert!(matches!(
+            inspect_all("REFRESH MATERIALIZED VIEW mv; SELCT 1"),
+            Err(InspectError::Parse { .. })
+        ));
+        // `inspect` looks at the first statement
+        let res = inspect(
+            "REFRESH MATERIALIZED VIEW mv; DELETE FROM users",
+            "postgres",
+        )
+        .unwrap();
+        assert_eq!(res.query_type, QueryType::REFRESH);
+    }
+
+    #[test]
+    fn locking() {
+        let res = inspect("SELECT id FROM users WHERE id = 1 FOR UPDATE", "generic").unwrap();
+        assert_eq!(res.query_type, QueryType::SELECT);
+        assert_eq!(res.locking.as_deref(), Some("FOR UPDATE"));
+        assert!(res.locked_tables.is_empty());
+
+        let res = inspect(
+            "SELECT u.id, o.id FROM users u JOIN orders o ON u.id = o.user_id FOR SHARE OF o NOWAIT", "generic",
+        ).unwrap();
+        assert_eq!(res.locking.as_deref(), Some("FOR SHARE"));
+        assert_eq!(res.locked_tables, vec!["orders"]);
+        assert_eq!(res.tables, vec!["orders", "users"]);
+
+        // sqlparser doesn't parse the key lock strengths
+        let res = inspect(
+            "SELECT id FROM users WHERE id = 1 FOR NO KEY UPDATE",
+            "postgres",
+        )
+        .unwrap();
+        assert_eq!(res.locking.as_deref(), Some("FOR NO KEY UPDATE"));
+        assert_eq!(res.access, Access::Write);
+        let res = inspect(
+            "SELECT u.id FROM users u JOIN orders o ON u.id = o.user_id FOR KEY SHARE OF u",
+            "postgres",
+        )
+        .unwrap();
+        assert_eq!(res.locking.as_deref(), Some("FOR KEY SHARE"));
+        assert_eq!(res.locked_tables, vec!["users"]);
+        let res = inspect(
+            "SELECT id FROM users FOR KEY SHARE OF users FOR UPDATE OF users; SELECT 1",
+            "postgres",
+        )
+        .unwrap();
+        assert_eq!(res.locking.as_deref(), Some("FOR UPDATE"));
+
+        let res = inspect("SELECT id FROM users", "generic").unwrap();
+        assert_eq!(res.locking, None);
+    }
+
+    #[test]
+    fn sql_comment() {
+        let res = inspect("SELECT id, name FROM users WHERE age > 30", "generic").unwrap();
+        assert_eq!(
+            res.to_sql_comment(),
+            "/* sql-inspector: tables=users; columns=age,id,name; type=SELECT */"
+        );
+
+        let res = inspect("SELECT \"odd*/col\", \"/*col\" FROM users", "generic").unwrap();
+        let comment = res.to_sql_comment();
+        assert_eq!(
+            comment,
+            "/* sql-inspector: tables=users; columns=/ *col,odd* /col; type=SELECT */"
+        );
+        assert_eq!(comment.matches("*/").count(), 1);
+        assert_eq!(comment.matches("/*").count(), 1);
+    }
+
+    #[test]
+    fn metric_label() {
+        let tests = vec![
+            ("SELECT 1", "select:0tables"),
+            ("UPDATE users SET age = 30", "update:1table"),
+            (
+                "SELECT * FROM users JOIN orders ON users.id = orders.user_id",
+                "select:2tables",
+            ),
+            (
+                "INSERT INTO t1 SELECT * FROM t2 JOIN t3 ON t2.id = t3.id",
+                "insert:3+tables",
+            ),
+            (
+                "CREATE VIEW v AS SELECT * FROM t1, t2, t3, t4",
+                "create_view:3+tables",
+            ),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(
+                inspect(sql, "generic").unwrap().metric_label(),
+                expected,
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn cross_database() {
+        let res = inspect(
+            "SELECT u.id, o.total FROM db1.users u JOIN db2.orders o ON u.id = o.user_id",
+            "mysql",
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["db1.users", "db2.orders"]);
+        assert_eq!(res.databases, vec!["db1", "db2"]);
+        assert!(res.cross_database);
+
+        let res = inspect(
+            "SELECT * FROM db1.users JOIN db1.orders ON users.id = orders.user_id",
+            "mysql",
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["db1.orders", "db1.users"]);
+        assert_eq!(res.databases, vec!["db1"]);
+        assert!(!res.cross_database);
+
+        // Unqualified tables don't have a database
+        let res = inspect(
+            "SELECT * FROM users JOIN orders ON users.id = orders.user_id",
+            "generic",
+        )
+        .unwrap();
+        assert!(res.databases.is_empty());
+        assert!(!res.cross_database);
+
+        // Outside MySQL, the two-part names are qualified by a schema
+        let sql = "SELECT o.id FROM public.orders o JOIN sales.users u ON u.id = o.user_id";
+        for dialect in ["postgres", "generic"] {
+            let res = inspect(sql, dialect).unwrap();
+            assert!(res.databases.is_empty(), "{dialect}");
+            assert_eq!(res.schemas, vec!["public", "sales"], "{dialect}");
+            assert!(!res.cross_database, "{dialect}");
+        }
+        let sql =
+            "SELECT o.id FROM db1.public.orders o JOIN db2.public.users u ON u.id = o.user_id";
+        let res = inspect(sql, "postgres").unwrap();
+        assert_eq!(res.databases, vec!["db1", "db2"]);
+        assert!(res.cross_database);
+    }
+
+    #[test]
+    fn create_view() {
+        let res = inspect(
+            "CREATE MATERIALIZED VIEW order_totals AS
+                SELECT u.id, SUM(o.total) FROM users u JOIN orders o ON u.id = o.user_id GROUP BY u.id", "generic",
+        ).unwrap();
+        assert_eq!(res.query_type, QueryType::CREATE_VIEW);
+        assert_eq!(res.target_table, "order_totals");
+        assert!(res.materialized);
+        assert_eq!(res.tables, vec!["order_totals", "orders", "users"]);
+        assert_eq!(
+            res.columns,
+            vec!["orders.total", "orders.user_id", "users.id"]
+        );
+
+        let res = inspect(
+            "CREATE VIEW active_users AS SELECT * FROM users WHERE active",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.query_type, QueryType::CREATE_VIEW);
+        assert_eq!(res.target_table, "active_users");
+        assert!(!res.materialized);
+        assert_eq!(res.tables, vec!["active_users", "users"]);
+        assert_eq!(res.columns, vec!["*", "active"]);
+    }
+
+    #[test]
+    fn bare_columns() {
+        let res = inspect(
+            "SELECT users.id, orders.id, name FROM users JOIN orders ON users.id = orders.user_id",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.bare_columns(), vec!["id", "name", "user_id"]);
+    }
+
+    #[test]
+    fn pagination() {
+        let tests = vec![
+            ("SELECT id FROM users", None, None, false),
+            ("SELECT id FROM users LIMIT 10", Some(10), None, true),
+            (
+                "SELECT id FROM users LIMIT 10 OFFSET 20",
+                Some(10),
+                Some(20),
+                true,
+            ),
+            (
+                "SELECT id FROM users LIMIT 20, 10",
+                Some(10),
+                Some(20),
+                true,
+            ),
+            (
+                "SELECT id FROM users ORDER BY id OFFSET 20 ROWS FETCH NEXT 10 ROWS ONLY",
+                Some(10),
+                Some(20),
+                true,
+            ),
+            (
+                "SELECT id FROM users FETCH FIRST 5 ROWS ONLY",
+                Some(5),
+                None,
+                true,
+            ),
+            ("SELECT id FROM users LIMIT $1 OFFSET $2", None, None, true),
+            ("SELECT id FROM users LIMIT ? OFFSET ?", None, None, true),
+            ("SELECT id FROM users LIMIT 2 * 5", None, None, true),
+            ("SELECT id FROM users OFFSET 20", None, Some(20), false),
+        ];
+
+        for (sql, limit, offset, has_limit) in tests {
+            let res = inspect(sql, "generic").unwrap();
+            assert_eq!(res.limit, limit, "{sql}");
+            assert_eq!(res.offset, offset, "{sql}");
+            assert_eq!(res.has_limit, has_limit, "{sql}");
+        }
+    }
+
+    #[test]
+    fn duplicate_projections() {
+        let res = inspect("SELECT id, id, name FROM users", "generic").unwrap();
+        assert_eq!(res.columns, vec!["id", "name"]);
+        assert_eq!(res.duplicate_projections, vec!["id"]);
+
+        // The same column through an alias
+        let res = inspect(
+            "SELECT u.id, users.id, u.name as a, u.name as b FROM users u",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.duplicate_projections, vec!["users.id", "users.name"]);
+
+        let res = inspect("SELECT id, name FROM users WHERE id > 1", "generic").unwrap();
+        assert!(res.duplicate_projections.is_empty());
+    }
+
+    #[test]
+    fn strict_unsupported() {
+        let sql = "CREATE INDEX idx ON users (id)";
+        // By default, unsupported statements are not rejected
+        assert!(inspect_with(sql, &InspectOptions::default()).is_ok());
+
+        let options = InspectOptions {
+            strict_unsupported: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            inspect_with(sql, &options).unwrap_err(),
+            InspectError::Unsupported("CreateIndex".to_string())
+        );
+        assert_eq!(
+            inspect_all_with("SELECT id FROM users; TRUNCATE users", &options).unwrap_err(),
+            InspectError::Unsupported("Truncate".to_string())
+        );
+        assert!(inspect_with("SELECT id FROM users", &options).is_ok());
+    }
+
+    #[test]
+    fn insert_from_table() {
+        let res = inspect("INSERT INTO target TABLE source", "generic").unwrap();
+        assert_eq!(res.query_type, QueryType::INSERT);
+        assert_eq!(res.target_table, "target");
+        assert_eq!(res.tables, vec!["source", "target"]);
+        assert!(!res.overwrite);
+
+        let res = inspect(
+            "INSERT OVERWRITE TABLE target SELECT id FROM source",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.target_table, "target");
+        assert_eq!(res.tables, vec!["source", "target"]);
+        assert!(res.overwrite);
+
+        let res = inspect("INSERT INTO users TABLE archive.users", "generic").unwrap();
+        assert_eq!(res.tables, vec!["archive.users", "users"]);
+    }
+
+    #[test]
+    fn query_type_only() {
+        let tests = vec![
+            ("SELECT id FROM users", QueryType::SELECT),
+            ("INSERT INTO users (id) VALUES (1)", QueryType::INSERT),
+            ("UPDATE users SET age = 30", QueryType::UPDATE),
+            ("DELETE FROM users WHERE age > 30", QueryType::DELETE),
+            (
+                "CREATE VIEW v AS SELECT id FROM users",
+                QueryType::CREATE_VIEW,
+            ),
+            ("UPDATE users SET age = 30; SELECT 1", QueryType::UPDATE),
+            (
+                "WITH a AS (SELECT id FROM t) INSERT INTO x SELECT id FROM a",
+                QueryType::INSERT,
+            ),
+            ("DROP TABLE users", QueryType::DROP),
+            ("TRUNCATE users", QueryType::UNSUPPORTED),
+            ("", QueryType::SELECT),
+            // The wrapped statement doesn't set the type
+            ("EXPLAIN SELECT * FROM users", QueryType::UNSUPPORTED),
+            ("EXPLAIN ANALYZE DELETE FROM users", QueryType::UNSUPPORTED),
+            ("PREPARE p AS SELECT id FROM users", QueryType::UNSUPPORTED),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(query_type(sql, "generic").unwrap(), expected, "{sql}");
+            // It agrees with `inspect`
+            let res = inspect(sql, "generic").unwrap();
+            assert_eq!(res.query_type, expected, "{sql}");
+        }
+        let res = inspect("EXPLAIN SELECT * FROM users", "generic").unwrap();
+        assert_eq!(res.access, Access::Write);
+        let options = InspectOptions {
+            strict_unsupported: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            inspect_with("EXPLAIN SELECT * FROM users", &options).unwrap_err(),
+            InspectError::Unsupported("Explain".to_string())
+        );
+
+        let sql = "REFRESH MATERIALIZED VIEW mv";
+        assert_eq!(query_type(sql, "postgres"), Ok(QueryType::REFRESH));
+        // The dialect is used to parse
+        let sql = "SELECT `id` FROM users";
+        assert_eq!(query_type(sql, "mysql"), Ok(QueryType::SELECT));
+        assert!(query_type(sql, "postgres").is_err());
+        assert!(matches!(
+            query_type("SELCT 1", "generic"),
+            Err(InspectError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn idempotent() {
+        let tests = vec![
+            ("SELECT id FROM users", true),
+            ("DELETE FROM users WHERE id = 1", true),
+            ("DELETE FROM users", true),
+            ("UPDATE users SET age = 30 WHERE id = 1", true),
+            ("UPDATE users SET visits = visits + 1 WHERE id = 1", false),
+            ("UPDATE users SET name = p.name FROM people p WHERE p.id = users.id", false),
+            ("INSERT INTO users (id, name) VALUES (1, 'Marco')", false),
+            (
+                "INSERT INTO users (id, name) VALUES (1, 'Marco') ON CONFLICT (id) DO NOTHING",
+                true,
+            ),
+            (
+                "INSERT INTO users (id, name) VALUES (1, 'Marco') ON DUPLICATE KEY UPDATE name = 'Marco'",
+                true,
+            ),
+            ("INSERT OVERWRITE TABLE users SELECT * FROM people", true),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(
+                inspect(sql, "generic").unwrap().idempotent,
+                expected,
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn constraint_subqueries() {
+        // The tables referenced by the constraint expressions are collected
+        let tests = vec![
+            (
+                "CREATE TABLE orders (id INT, user_id INT CHECK (EXISTS (SELECT 1 FROM users WHERE users.id = user_id)))",
+                vec!["orders", "users"],
+            ),
+            (
+                "CREATE TABLE orders (id INT, CONSTRAINT c CHECK (EXISTS (SELECT 1 FROM users u WHERE u.id = id)))",
+                vec!["orders", "users"],
+            ),
+            (
+                "ALTER TABLE orders ADD CONSTRAINT c CHECK (user_id IN (SELECT id FROM users))",
+                vec!["orders", "users"],
+            ),
+        ];
+        for (sql, tables) in tests {
+            assert_eq!(inspect(sql, "generic").unwrap().tables, tables, "{sql}");
+        }
+    }
+
+    #[test]
+    fn multi_part_assignments() {
+        let res = inspect(
+            "UPDATE t1 SET t1.a = t2.b FROM t2 WHERE t1.id = t2.id",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.columns, vec!["t1.a", "t1.id", "t2.b", "t2.id"]);
+    }
+
+    #[test]
+    fn lock_tables() {
+        let sql = "LOCK TABLES users WRITE, orders AS o READ, logs LOW_PRIORITY WRITE";
+        let options = InspectOptions {
+            dialect: Dialect::MySql,
+            ..Default::default()
+        };
+        let res = inspect_with(sql, &options).unwrap();
+        assert_eq!(res.query_type, QueryType::LOCK);
+        assert_eq!(res.tables, vec!["logs", "orders", "users"]);
+        assert_eq!(
+            res.locks,
+            vec![
+                ("users".to_string(), "WRITE".to_string()),
+                ("orders".to_string(), "READ".to_string()),
+                ("logs".to_string(), "LOW_PRIORITY WRITE".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn batch() {
+        let sqls = vec![
+            "SELECT id FROM users".to_string(),
+            "SELCT id FROM users".to_string(),
+            "DELETE FROM orders".to_string(),
+        ];
+        let res = inspect_batch(&sqls, "generic");
+        assert_eq!(res.len(), 3);
+        assert!(matches!(&res[0], InspectResponse::Result(r) if r.tables == vec!["users"]));
+        assert!(matches!(&res[1], InspectResponse::Error { error } if !error.message.is_empty()));
+        assert!(
+            matches!(&res[1], InspectResponse::Error { error } if error.near.as_deref() == Some("SELCT"))
+        );
+        assert!(matches!(&res[2], InspectResponse::Result(r) if r.query_type == QueryType::DELETE));
+
+        // The queries are parsed with the dialect
+        let sqls = vec!["SELECT `id` FROM users".to_string()];
+        assert!(matches!(
+            &inspect_batch(&sqls, "mysql")[0],
+            InspectResponse::Result(_)
+        ));
+        assert!(matches!(
+            &inspect_batch(&sqls, "postgres")[0],
+            InspectResponse::Error { .. }
+        ));
+    }
+
+    #[test]
+    fn js_safe_numbers() {
+        // The row counts above `Number.MAX_SAFE_INTEGER` are capped for JS
+        let sql = "SELECT id FROM users LIMIT 5, 18446744073709551615";
+        let res = inspect(sql, "mysql").unwrap();
+        assert_eq!(res.limit, Some(u64::MAX));
+        let InspectResponse::Result(res) = InspectResponse::from(Ok(res)) else {
+            panic!("{sql}");
+        };
+        assert_eq!(res.limit, Some(9007199254740991));
+        assert_eq!(res.offset, Some(5));
+
+        // and so are the positions
+        let sql = "SELECT a FROM t ORDER BY 9007199254740993";
+        let res = inspect(sql, "generic").unwrap();
+        assert_eq!(
+            res.warnings,
+            vec![Warning::UnresolvedPosition(9007199254740993)]
+        );
+        let InspectResponse::Result(res) = InspectResponse::from(Ok(res)) else {
+            panic!("{sql}");
+        };
+        assert_eq!(
+            res.warnings,
+            vec![Warning::UnresolvedPosition(9007199254740991)]
+        );
+    }
+
+    #[test]
+    fn fold_case() {
+        let sql =
+            r#"SELECT Id, "Name", U.Email FROM Users U JOIN Orders ON U.Id = Orders."User_Id""#;
+        let with = |dialect, fold_case| {
+            let options = InspectOptions {
+                dialect,
+                fold_case,
+                ..Default::default()
+            };
+            inspect_with(sql, &options).unwrap()
+        };
+
+        // Postgres folds the unquoted identifiers to lowercase
+        let res = with(Dialect::Postgres, Casing::Dialect);
+        assert_eq!(
+            res.columns,
+            vec!["Name", "id", "orders.User_Id", "users.email", "users.id"]
+        );
+        assert_eq!(res.tables, vec!["orders", "users"]);
+
+        // The generic dialect preserves them
+        let res = with(Dialect::Generic, Casing::Dialect);
+        assert_eq!(
+            res.columns,
+            vec!["Id", "Name", "Orders.User_Id", "Users.Email", "Users.Id"]
+        );
+        assert_eq!(res.tables, vec!["Orders", "Users"]);
+
+        // Manual folding applies to any dialect
+        let res = with(Dialect::Generic, Casing::Upper);
+        assert_eq!(
+            res.columns,
+            vec!["ID", "Name", "ORDERS.User_Id", "USERS.EMAIL", "USERS.ID"]
+        );
+        assert_eq!(res.tables, vec!["ORDERS", "USERS"]);
+    }
+
+    #[test]
+    fn refresh_materialized_view() {
+        let res = inspect_with(
+            "REFRESH MATERIALIZED VIEW order_totals",
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(res.query_type, QueryType::REFRESH);
+        assert_eq!(res.target_table, "order_totals");
+        assert_eq!(res.tables, vec!["order_totals"]);
+
+        let views = HashMap::from([
+            (
+                "order_totals".to_string(),
+                "SELECT u.id, SUM(o.total) FROM users u JOIN orders o ON u.id = o.user_id GROUP BY u.id".to_string(),
+            ),
+            (
+                "top_customers".to_string(),
+                "SELECT id FROM order_totals JOIN vip ON order_totals.id = vip.id".to_string(),
+            ),
+        ]);
+        let res = inspect_with_views(
+            "REFRESH MATERIALIZED VIEW CONCURRENTLY top_customers WITH DATA;",
+            &views,
+        )
+        .unwrap();
+        assert_eq!(res.query_type, QueryType::REFRESH);
+        assert_eq!(res.target_table, "top_customers");
+        assert_eq!(
+            res.tables,
+            vec!["order_totals", "orders", "top_customers", "users", "vip"]
+        );
+    }
+
+    #[test]
+    fn max_depth() {
+        let tests = vec![
+            ("SELECT id FROM users", 0),
+            ("SELECT id FROM users UNION SELECT id FROM customers", 0),
+            ("SELECT id FROM users WHERE id IN (SELECT user_id FROM orders)", 1),
+            ("WITH o AS (SELECT user_id FROM orders) SELECT * FROM o", 1),
+            (
+                "SELECT id FROM users WHERE id IN (SELECT user_id FROM orders WHERE EXISTS (SELECT 1 FROM items WHERE items.order_id = orders.id))",
+                2,
+            ),
+            ("SELECT x FROM (SELECT x FROM (SELECT 1 AS x) a) b", 2),
+            ("INSERT INTO t1 (a) SELECT b FROM t2", 0),
+            ("UPDATE users SET age = 30", 0),
+            ("DELETE FROM users WHERE id IN (SELECT user_id FROM banned)", 1),
+        ];
+        for (sql, depth) in tests {
+            assert_eq!(inspect(sql, "generic").unwrap().max_depth, depth, "{sql}");
+        }
+    }
+
+    #[test]
+    fn table_edges_list() {
+        let edge = |from: &str, to: &str| (from.to_string(), to.to_string());
+        let tests = vec![
+            ("SELECT id FROM users", vec![]),
+            (
+                "SELECT * FROM users u JOIN orders o ON o.user_id = u.id",
+                vec![edge("orders", "users")],
+            ),
+            (
+                // each table is paired with the tables of its ON condition
+                "SELECT * FROM a JOIN b ON a.id = b.a_id JOIN c ON b.id = c.b_id",
+                vec![edge("a", "b"), edge("b", "c")],
+            ),
+            (
+                // without qualifiers, with the previous table
+                "SELECT * FROM a JOIN b USING (id) CROSS JOIN c",
+                vec![edge("a", "b"), edge("b", "c")],
+            ),
+            (
+                "INSERT INTO archive SELECT * FROM users u JOIN orders o ON o.user_id = u.id",
+                vec![
+                    edge("orders", "archive"),
+                    edge("orders", "users"),
+                    edge("users", "archive"),
+                ],
+            ),
+            (
+                "UPDATE users SET n = (SELECT COUNT(*) FROM orders WHERE orders.user_id = users.id)",
+                vec![edge("orders", "users")],
+            ),
+            (
+                "CREATE VIEW v AS SELECT * FROM users; SELECT * FROM v JOIN users ON v.id = users.id",
+                vec![edge("users", "v")],
+            ),
+            (
+                // the CTEs are not tables
+                "WITH a AS (SELECT * FROM t) INSERT INTO x SELECT * FROM a",
+                vec![edge("t", "x")],
+            ),
+            (
+                "MERGE INTO x USING s ON x.id = s.id WHEN MATCHED THEN UPDATE SET a = s.a",
+                vec![edge("s", "x")],
+            ),
+            (
+                "CREATE TABLE x AS SELECT * FROM t",
+                vec![edge("t", "x")],
+            ),
+            ("CREATE TABLE x (id INT)", vec![]),
+        ];
+        for (sql, edges) in tests {
+            assert_eq!(table_edges(sql, "generic").unwrap(), edges, "{sql}");
+        }
+        // The dialect is used to parse
+        let sql = "INSERT INTO `archive` SELECT * FROM `users`";
+        assert_eq!(
+            table_edges(sql, "mysql").unwrap(),
+            vec![edge("users", "archive")]
+        );
+        assert!(table_edges(sql, "postgres").is_err());
+        assert!(matches!(
+            table_edges("SELEC id FROM users", "generic"),
+            Err(InspectError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn deterministic_output() {
+        let sql = "SELECT u.name, o.total, p.title, z.a, y.b FROM users u \
+                   JOIN orders o ON o.user_id = u.id JOIN products p ON p.id = o.product_id \
+                   JOIN zeta z ON z.id = u.id JOIN yotta y ON y.id = z.id";
+        let expected = format!("{:?}", inspect(sql, "generic").unwrap());
+        for _ in 0..20 {
+            assert_eq!(format!("{:?}", inspect(sql, "generic").unwrap()), expected);
+        }
+    }
+
+    #[test]
+    fn call() {
+        let res = inspect("CALL archive_orders(30)", "generic").unwrap();
+        assert_eq!(res.query_type, QueryType::CALL);
+        assert_eq!(res.procedures, vec!["archive_orders"]);
+        assert!(res.tables.is_empty());
+        assert!(res.columns.is_empty());
+        assert!(!res.idempotent);
+
+        let res = inspect("CALL billing.close_month(account_id, 'EUR')", "generic").unwrap();
+        assert_eq!(res.procedures, vec!["billing.close_month"]);
+        assert_eq!(res.columns, vec!["account_id"]);
+
+        assert_eq!(query_type("CALL p()", "generic"), Ok(QueryType::CALL));
+    }
+
+    #[test]
+    fn predicate_values() {
+        let pair = |column: &str, value: &str| (column.to_string(), value.to_string());
+        let tests = vec![
+            ("SELECT id FROM accounts", vec![]),
+            (
+                "SELECT id FROM accounts WHERE balance < -100 AND 0 < +5",
+                vec![pair("balance", "-100")],
+            ),
+            (
+                "SELECT id FROM events e WHERE e.day >= DATE '2021-01-01' AND e.at < TIMESTAMP '2021-01-02 00:00:00'",
+                vec![
+                    pair("events.at", "TIMESTAMP '2021-01-02 00:00:00'"),
+                    pair("events.day", "DATE '2021-01-01'"),
+                ],
+            ),
+            (
+                "SELECT id FROM users WHERE active = true AND NOT deleted = FALSE AND name <> 'x'",
+                vec![
+                    pair("active", "true"),
+                    pair("deleted", "false"),
+                    pair("name", "'x'"),
+                ],
+            ),
+            (
+                "SELECT id FROM t WHERE 1.5 = ratio AND kind IN ('a', (-2)) AND n BETWEEN -1 AND 10",
+                vec![
+                    pair("kind", "'a'"),
+                    pair("kind", "-2"),
+                    pair("n", "-1"),
+                    pair("n", "10"),
+                    pair("ratio", "1.5"),
+                ],
+            ),
+            ("SELECT id FROM t WHERE a = $1 AND b = c", vec![]),
+            (
+                "UPDATE users SET name = 'x' WHERE id = 42",
+                vec![pair("id", "42")],
+            ),
+        ];
+        for (sql, values) in tests {
+            assert_eq!(
+                inspect(sql, "generic").unwrap().predicate_values,
+                values,
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn single() {
+        let res = inspect_single("SELECT id FROM users;").unwrap();
+        assert_eq!(res.tables, vec!["users"]);
+        assert_eq!(
+            inspect_single("SELECT id FROM users; DELETE FROM orders").unwrap_err(),
+            InspectError::MultipleStatements(2)
+        );
+        assert_eq!(
+            inspect_single("SELECT 1; SELECT 2; SELECT 3")
+                .unwrap_err()
+                .to_string(),
+            "expected a single statement, found 3"
+        );
+        assert!(matches!(
+            inspect_single("SELCT id FROM users"),
+            Err(InspectError::Parse { .. })
+        ));
+        assert_eq!(
+            inspect_single("REFRESH MATERIALIZED VIEW v")
+                .unwrap()
+                .query_type,
+            QueryType::REFRESH
+        );
+
+        let options = InspectOptions {
+            dialect: Dialect::MySql,
+            ..Default::default()
+        };
+        let res = inspect_single_with("SELECT `id` FROM users", &options).unwrap();
+        assert_eq!(res.columns, vec!["id"]);
+        assert!(inspect_single("SELECT `id` FROM users; SELECT 1").is_err());
+    }
+
+    #[test]
+    fn predicate_count() {
+        let tests =
+            vec![
+            ("SELECT id FROM t", 0),
+            ("SELECT id FROM t WHERE a = 1", 1),
+            ("SELECT id FROM t WHERE a = 1 AND b > 2 AND c < 3", 3),
+            ("SELECT id FROM t WHERE a = 1 AND (b > 2 OR (c < 3 AND NOT (d = 4 OR e IS NULL)))", 5),
+            ("SELECT id FROM t WHERE a IN (SELECT a FROM u WHERE b = 1 OR c = 2)", 3),
+            ("SELECT id FROM t WHERE a = 1 UNION SELECT id FROM u WHERE b = 2", 2),
+            ("SELECT id FROM t JOIN u ON t.id = u.id AND u.x = 1", 0),
+            ("UPDATE t SET a = 1 WHERE b = 2 OR c = 3", 2),
+            ("DELETE FROM t WHERE active", 1),
+        ];
+        for (sql, count) in tests {
+            assert_eq!(
+                inspect(sql, "generic").unwrap().predicate_count,
+                count,
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn sequences() {
+        let tests = vec![
+            ("SELECT id FROM users", vec![]),
+            ("SELECT nextval('order_seq')", vec!["order_seq"]),
+            (
+                "INSERT INTO orders (id) VALUES (NEXTVAL('public.order_seq'::regclass))",
+                vec!["public.order_seq"],
+            ),
+            (
+                "SELECT currval('a_seq'), pg_catalog.setval('b_seq', max(id)) FROM t",
+                vec!["a_seq", "b_seq"],
+            ),
+            // not a literal, the sequence is unknown
+            ("SELECT nextval(name) FROM seqs", vec![]),
+        ];
+        for (sql, sequences) in tests {
+            assert_eq!(
+                inspect(sql, "generic").unwrap().sequences,
+                sequences,
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn warnings() {
+        let ambiguous = |column: &str| Warning::AmbiguousColumn(column.to_string());
+        let tests = vec![
+            (
+                "SELECT id, name FROM users WHERE age > 18 ORDER BY 2",
+                vec![],
+            ),
+            (
+                "SELECT address, t1.name FROM t1 JOIN t2 ON t1.id = t2.id WHERE age > 1",
+                vec![ambiguous("address"), ambiguous("age")],
+            ),
+            // the columns of a subquery have their own scope
+            (
+                "SELECT t1.id FROM t1, t2 WHERE t1.id IN (SELECT id FROM t3)",
+                vec![],
+            ),
+            (
+                "SELECT a, COUNT(*) FROM t GROUP BY 1 ORDER BY 3, 0",
+                vec![
+                    Warning::UnresolvedPosition(0),
+                    Warning::UnresolvedPosition(3),
+                ],
+            ),
+            ("SELECT * FROM t ORDER BY 3", vec![]),
+            (
+                "INSERT INTO t (a, b) VALUES (1, 2) ON DUPLICATE KEY UPDATE b = VALUES(b) + 1",
+                vec![Warning::DeprecatedSyntax(
+                    "VALUES() in ON DUPLICATE KEY UPDATE".to_string(),
+                )],
+            ),
+        ];
+        for (sql, warnings) in tests {
+            assert_eq!(inspect(sql, "generic").unwrap().warnings, warnings, "{sql}");
+        }
+
+        let views = HashMap::from([
+            (
+                "v1".to_string(),
+                "SELECT * FROM v2 JOIN users ON v2.id = users.id".to_string(),
+            ),
+            ("v2".to_string(), "SELECT * FROM v1".to_string()),
+        ]);
+        let options = InspectOptions {
+            views,
+            ..Default::default()
+        };
+        let res = inspect_with("REFRESH MATERIALIZED VIEW v1", &options).unwrap();
+        assert_eq!(res.tables, vec!["users", "v1", "v2"]);
+        assert_eq!(res.warnings, vec![Warning::CircularView("v1".to_string())]);
+    }
+
+    #[test]
+    fn blast_radius() {
+        let tests = vec![
+            (
+                "UPDATE users SET name = 'x' WHERE id = 42",
+                BlastRadius::SingleRow,
+            ),
+            (
+                "UPDATE users u SET name = $2 WHERE u.id = $1 AND active",
+                BlastRadius::SingleRow,
+            ),
+            (
+                "DELETE FROM users WHERE id = 1 OR id = 2",
+                BlastRadius::Filtered,
+            ),
+            ("DELETE FROM users WHERE user_id = 1", BlastRadius::Filtered),
+            (
+                "UPDATE users SET name = 'x' WHERE id = other_id",
+                BlastRadius::Filtered,
+            ),
+            ("SELECT name FROM users", BlastRadius::FullTable),
+            ("DELETE FROM users", BlastRadius::FullTable),
+            ("INSERT INTO users (id) VALUES (1)", BlastRadius::SingleRow),
+            (
+                "INSERT INTO users (id) VALUES (1), (2)",
+                BlastRadius::Filtered,
+            ),
+            (
+                "INSERT INTO archive SELECT * FROM users WHERE age > 3",
+                BlastRadius::Filtered,
+            ),
+            (
+                "INSERT INTO archive SELECT * FROM users",
+                BlastRadius::MultiTable,
+            ),
+        ];
+        for (sql, radius) in tests {
+            assert_eq!(
+                inspect(sql, "generic").unwrap().blast_radius(),
+                radius,
+                "{sql}"
+            );
+        }
+        let options = InspectOptions {
+            dialect: Dialect::MySql,
+            ..Default::default()
+        };
+        let res = inspect_with("DELETE t1, t2 FROM t1 JOIN t2 ON t1.id = t2.id", &options);
+        assert_eq!(res.unwrap().blast_radius(), BlastRadius::MultiTable);
+        assert!(BlastRadius::SingleRow < BlastRadius::MultiTable);
+    }
+
+    #[test]
+    fn qualify_insert_columns() {
+        let sql = "INSERT INTO users (id, name) SELECT p.id, name FROM people p";
+        let res = inspect(sql, "generic").unwrap();
+        assert_eq!(
+            res.columns,
+            vec!["name", "people.id", "users.id", "users.name"]
+        );
+
+        let options = InspectOptions {
+            qualify_insert_columns: false,
+            ..Default::default()
+        };
+        let res = inspect_with(sql, &options).unwrap();
+        assert_eq!(res.columns, vec!["id", "name", "people.id"]);
+        assert_eq!(res.target_table, "users");
+    }
+
+    #[test]
+    fn invalid_aggregation() {
+        let tests = vec![
+            ("SELECT dept, COUNT(*) FROM employees", true),
+            (
+                "SELECT e.dept AS d, max(e.salary) + 1 FROM employees e",
+                true,
+            ),
+            ("SELECT dept, COUNT(*) FROM employees GROUP BY dept", false),
+            ("SELECT COUNT(*), SUM(salary) FROM employees", false),
+            (
+                "SELECT dept, SUM(salary) OVER (PARTITION BY dept) FROM employees",
+                false,
+            ),
+            ("SELECT dept FROM employees", false),
+            ("SELECT id FROM t WHERE x > (SELECT y, AVG(z) FROM u)", true),
+        ];
+        for (sql, invalid) in tests {
+            assert_eq!(
+                inspect(sql, "generic").unwrap().invalid_aggregation,
+                invalid,
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn column_types() {
+        let res = inspect(
+            "CREATE TABLE users (
+                id INTEGER PRIMARY KEY,
+                name VARCHAR(255) NOT NULL,
+                price NUMERIC(10, 2),
+                tags TEXT[],
+                scores INT[3][3],
+                created_at TIMESTAMP WITH TIME ZONE
+            )",
+            "generic",
+        )
+        .unwrap();
+        let types: Vec<(&str, &str)> = res
+            .column_types
+            .iter()
+            .map(|(column, data_type)| (column.as_str(), data_type.as_str()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                ("users.created_at", "TIMESTAMP WITH TIME ZONE"),
+                ("users.id", "INTEGER"),
+                ("users.name", "VARCHAR(255)"),
+                ("users.price", "NUMERIC(10,2)"),
+                ("users.scores", "INT[3][3]"),
+                ("users.tags", "TEXT[]"),
+            ]
+        );
+        assert!(inspect("SELECT id FROM users", "generic")
+            .unwrap()
+            .column_types
+            .is_empty());
+    }
+
+    #[test]
+    fn scan_tables_lossy_fallback() {
+        let tests = vec![
+            ("SELCT name FROM users WHERE", vec!["users"]),
+            (
+                "SELECT * FROM users u, public.orders AS o JOIN items i ON i.id = o.item_id",
+                vec!["items", "public.orders", "users"],
+            ),
+            (
+                "INSERT INTO logs SELECT * FROM (SELECT id FROM events) e",
+                vec!["events", "logs"],
+            ),
+            (
+                "UPDATE `users` SET name = 'x' WHERE id IN (SELECT id FROM orders FOR UPDATE",
+                vec!["orders", "users"],
+            ),
+            (
+                "SELECT name FROM users WHERE name = 'unterminated",
+                vec!["users"],
+            ),
+            ("SELECT 1", vec![]),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(scan_tables_lossy(sql), expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn same_objects_check() {
+        let tests =
+            vec![
+            (
+                "SELECT name, id FROM users u JOIN orders o ON o.user_id = u.id",
+                "SELECT users.id, users.name FROM orders JOIN users ON users.id = orders.user_id",
+                false,
+            ),
+            (
+                "SELECT name, id FROM users WHERE age > 3",
+                "SELECT id, name FROM users WHERE age < 10 ORDER BY name",
+                true,
+            ),
+            (
+                "SELECT u.id FROM users u JOIN orders o ON u.id = o.user_id",
+                "SELECT x.user_id, y.id FROM orders x, users y",
+                true,
+            ),
+            ("SELECT id FROM users", "DELETE FROM users WHERE id = 1", false),
+            ("SELECT id FROM users", "SELECT id, name FROM users", false),
+            ("SELECT id FROM users", "SELECT id FROM customers", false),
+        ];
+        for (a, b, same) in tests {
+            assert_eq!(
+                same_objects(
+                    &inspect(a, "generic").unwrap(),
+                    &inspect(b, "generic").unwrap()
+                ),
+                same,
+                "{a} / {b}"
+            );
+        }
+    }
+
+    #[test]
+    fn alias_map() {
+        let res = inspect(
+            "SELECT u.name AS user_name, o.total FROM users u \
+             JOIN orders AS o ON o.user_id = u.id \
+             WHERE u.id IN (SELECT b.user_id FROM db.banned b)",
+            "generic",
+        )
+        .unwrap();
+        let aliases: Vec<(&str, &str)> = res
+            .alias_map
+            .iter()
+            .map(|(alias, table)| (alias.as_str(), table.as_str()))
+            .collect();
+        assert_eq!(
+            aliases,
+            vec![("b", "db.banned"), ("o", "orders"), ("u", "users")]
+        );
+        assert!(inspect("SELECT id FROM users", "generic")
+            .unwrap()
+            .alias_map
+            .is_empty());
+
+        // an alias of an alias
+        let res = inspect(
+            "SELECT x.id FROM users u JOIN (SELECT * FROM u AS x) AS d ON d.id = u.id",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(
+            res.alias_map,
+            BTreeMap::from([
+                ("u".to_string(), "users".to_string()),
+                ("x".to_string(), "users".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn expand_wildcards() {
+        let schema = HashMap::from([
+            (
+                "users".to_string(),
+                vec!["id".to_string(), "name".to_string()],
+            ),
+            (
+                "orders".to_string(),
+                vec!["id".to_string(), "user_id".to_string()],
+            ),
+        ]);
+        let res = inspect_with_schema("SELECT * FROM users u WHERE u.id > 1", &schema).unwrap();
+        assert_eq!(res.columns, vec!["users.id", "users.name"]);
+        assert!(res.warnings.is_empty());
+
+        let res = inspect_with_schema(
+            "INSERT INTO archive SELECT * FROM users JOIN orders ON orders.user_id = users.id",
+            &schema,
+        )
+        .unwrap();
+        assert_eq!(
+            res.columns,
+            vec!["orders.id", "orders.user_id", "users.id", "users.name"]
+        );
+
+        let sql = "SELECT * FROM users, payments, (SELECT 1 AS one) AS sub";
+        let policy = |wildcard_policy| InspectOptions {
+            schema: schema.clone(),
+            expand_wildcards: true,
+            wildcard_policy,
+            ..Default::default()
+        };
+
+        let res = inspect_with(sql, &policy(WildcardPolicy::KeepStar)).unwrap();
+        assert_eq!(res.columns, vec!["*", "users.id", "users.name"]);
+        assert_eq!(
+            res.warnings,
+            vec![
+                Warning::UnexpandedWildcard("payments".to_string()),
+                Warning::UnexpandedWildcard("sub".to_string()),
+            ]
+        );
+
+        let res = inspect_with(sql, &policy(WildcardPolicy::Drop)).unwrap();
+        assert_eq!(res.columns, vec!["users.id", "users.name"]);
+        assert!(res.warnings.is_empty());
+
+        assert_eq!(
+            inspect_with(sql, &policy(WildcardPolicy::Error)).unwrap_err(),
+            InspectError::UnexpandedWildcard("payments".to_string())
+        );
+
+        // without the option, the `*` is kept as is
+        let options = InspectOptions {
+            schema,
+            ..Default::default()
+        };
+        assert_eq!(
+            inspect_with("SELECT * FROM users", &options)
+                .unwrap()
+                .columns,
+            vec!["*"]
+        );
+    }
+
+    #[test]
+    fn parse_error() {
+        let snippet = |sql: &str| match inspect(sql, "generic") {
+            Err(InspectError::Parse { snippet, .. }) => snippet,
+            res => panic!("expected a parse error for {sql}, got {res:?}"),
+        };
+        assert_eq!(snippet("SELCT 1"), "SELCT");
+        assert_eq!(snippet("SELECT name FROM users\nWHERE id = = 1"), "=");
+        assert_eq!(snippet("SELECT 'abc"), "'abc");
+        // at the end of the input
+        assert_eq!(snippet("SELECT id FROM"), "FROM");
+        assert!(inspect("SELECT id FROM users", "generic").is_ok());
+    }
+
+    #[test]
+    fn dialect() {
+        assert_eq!(dialect_from_str("postgres"), Dialect::Postgres);
+        assert_eq!(dialect_from_str("MySQL"), Dialect::MySql);
+        assert_eq!(dialect_from_str("sqlite"), Dialect::Sqlite);
+        assert_eq!(dialect_from_str("mssql"), Dialect::MsSql);
+        assert_eq!(dialect_from_str("ansi"), Dialect::Ansi);
+        assert_eq!(dialect_from_str("Snowflake"), Dialect::Snowflake);
+        assert_eq!(dialect_from_str("generic"), Dialect::Generic);
+        assert_eq!(dialect_from_str("oracle"), Dialect::Generic);
+
+        let res = inspect("SELECT `id`, `u`.`name` FROM `users` `u`", "mysql").unwrap();
+        assert_eq!(res.columns, vec!["id", "users.name"]);
+        assert_eq!(res.tables, vec!["users"]);
+
+        let res = inspect(
+            "SELECT id::text FROM users WHERE tags @> ARRAY['a']",
+            "postgres",
+        )
+        .unwrap();
+        assert_eq!(res.columns, vec!["id", "tags"]);
+
+        // `LOCK TABLES` is MySQL syntax
+        assert!(inspect("LOCK TABLES users READ", "generic").is_err());
+        assert!(inspect("LOCK TABLES users READ", "mysql").is_ok());
+    }
+
+    #[test]
+    fn group_by() {
+        let tests = vec![
+            ("SELECT id FROM users", vec![]),
+            (
+                "SELECT u.dept, COUNT(*) FROM users u GROUP BY u.dept, 2",
+                vec!["users.dept"],
+            ),
+            (
+                "SELECT COUNT(*) FROM orders o GROUP BY DATE_TRUNC('month', o.created_at), region",
+                vec!["DATE_TRUNC('month', o.created_at)", "region"],
+            ),
+            (
+                "SELECT o.region, o.year, SUM(o.total) FROM orders o GROUP BY ALL",
+                vec!["orders.region", "orders.year"],
+            ),
+            (
+                "SELECT dept FROM t1 GROUP BY dept UNION SELECT kind FROM t2 GROUP BY kind",
+                vec!["dept", "kind"],
+            ),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(inspect(sql, "generic").unwrap().group_by, expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn ctes() {
+        let res = inspect(
+            "WITH a AS (SELECT * FROM t1), \
+             b AS (SELECT a.id FROM a JOIN t2 ON a.id = t2.id) \
+             SELECT * FROM b",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["t1", "t2"]);
+        assert_eq!(res.ctes, vec!["a", "b"]);
+        assert_eq!(
+            res.cte_dependencies,
+            vec![("b".to_string(), "a".to_string())]
+        );
+
+        let res = inspect(
+            "WITH RECURSIVE tree AS (\
+             SELECT id FROM nodes WHERE parent_id IS NULL \
+             UNION ALL SELECT n.id FROM nodes n JOIN tree ON n.parent_id = tree.id) \
+             SELECT id FROM tree",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["nodes"]);
+        assert_eq!(res.ctes, vec!["tree"]);
+        assert!(res.cte_dependencies.is_empty());
+
+        // The columns of the CTE body are extracted
+        let res = inspect(
+            "WITH recent AS (SELECT id, total FROM orders WHERE placed_at > '2024-01-01') \
+             SELECT * FROM recent",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["orders"]);
+        assert_eq!(res.columns, vec!["*", "id", "placed_at", "total"]);
+
+        // The CTEs of a view definition are not tables either
+        let views = HashMap::from([(
+            "recent_totals".to_string(),
+            "WITH recent AS (SELECT * FROM orders) SELECT SUM(total) FROM recent".to_string(),
+        )]);
+        let res = inspect_with_views("REFRESH MATERIALIZED VIEW recent_totals", &views).unwrap();
+        assert_eq!(res.tables, vec!["orders", "recent_totals"]);
+    }
+
+    #[test]
+    fn base_tables_only() {
+        let sql = "WITH c AS (SELECT id FROM t1) \
+                   SELECT * FROM c, (SELECT id FROM t2) d, generate_series(1, 10) g, t3";
+        let res = inspect(sql, "generic").unwrap();
+        assert_eq!(res.tables, vec!["generate_series", "t1", "t2", "t3"]);
+
+        let options = InspectOptions {
+            base_tables_only: true,
+            ..Default::default()
+        };
+        let res = inspect_with(sql, &options).unwrap();
+        assert_eq!(res.tables, vec!["t1", "t2", "t3"]);
+        assert_eq!(res.ctes, vec!["c"]);
+    }
+
+    #[test]
+    fn order_by() {
+        let column = |column: &str, asc: Option<bool>, ordinal: bool| OrderByColumn {
+            column: column.to_string(),
+            asc,
+            ordinal,
+        };
+        let tests = vec![
+            ("SELECT id FROM users", vec![]),
+            (
+                "SELECT u.name, u.age FROM users u ORDER BY u.name DESC, age ASC, 2",
                 vec![
-                    "Table1.examno", 
-                    "Table1.serialno", 
-                    "Table1.test_date", 
-                    "Table1.testno",
-                    "Table2.ended", 
-                    "Table2.started", 
-                    "Table2.testno", 
-                    "Table3.testno", 
-                    "type",
+                    column("users.name", Some(false), false),
+                    column("age", Some(true), false),
+                    column("2", None, true),
                 ],
-                vec!["Table1", "Table2", "Table3"]
-            ), (
-                // multiple joins
-                "SELECT customerName, customercity, customermail, ordertotal, salestotal
-                FROM table1 AS t1
-                INNER JOIN table2 AS t2 ON t1.customerid = t2.customerid
-                LEFT JOIN
-                    table3 AS t3
-                    ON t2.orderId = t3.orderId
-                    WHERE t3.salesId IS NULL",
+            ),
+            (
+                "SELECT id FROM orders ORDER BY LOWER(region)",
+                vec![column("LOWER(region)", None, false)],
+            ),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(inspect(sql, "generic").unwrap().order_by, expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn joins() {
+        let join = |left: &str, right: &str, join_type: JoinType, columns: Vec<&str>| JoinInfo {
+            left_table: left.to_string(),
+            right_table: right.to_string(),
+            join_type,
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+        };
+        let tests = vec![
+            ("SELECT id FROM users", vec![]),
+            (
+                "SELECT * FROM users u LEFT OUTER JOIN orders o ON o.user_id = u.id \
+                 JOIN items i ON i.order_id = o.id RIGHT JOIN shops USING (shop_id)",
                 vec![
-                    "customerName", 
-                    "customercity", 
-                    "customermail", 
-                    "ordertotal", 
-                    "salestotal",
-                    "table1.customerid", 
-                    "table2.customerid",
-                    "table2.orderId",
-                    "table3.orderId", 
-                    "table3.salesId"
+                    join(
+                        "users",
+                        "orders",
+                        JoinType::Left,
+                        vec!["orders.user_id", "users.id"],
+                    ),
+                    join(
+                        "orders",
+                        "items",
+                        JoinType::Inner,
+                        vec!["items.order_id", "orders.id"],
+                    ),
+                    join("items", "shops", JoinType::Right, vec!["shop_id"]),
                 ],
-                vec!["table1", "table2", "table3"]
-            ),(
-                // complex query with join, counts and group by 
-                "SELECT
-                    t1.id, 
-                    t1.label_real_address, 
-                    t1.ext, 
-                    COUNT(t2.contact_id), 
-                    COUNT(t4.release_id) 
-                FROM
-                    table1 t1
-                    LEFT JOIN table2 t2  ON t2.contact_type='lx' AND t2.contact_id=t1.id 
-                    LEFT JOIN table3 t3 ON t3.id=t1.id 
-                    LEFT JOIN table4 t4 ON t3.release_id=t4.release_id 
-                GROUP BY t1.label_real_address 
-                ORDER BY COUNT(t2.contact_id) DESC", 
+            ),
+            (
+                "SELECT * FROM a FULL JOIN b ON a.id = b.id CROSS JOIN c, d",
                 vec![
-                    "table1.ext", 
-                    "table1.id", 
-                    "table1.label_real_address",
-                    "table2.contact_id", 
-                    "table2.contact_type",
-                    "table3.id", 
-                    "table3.release_id",
-                    "table4.release_id", 
+                    join("a", "b", JoinType::Full, vec!["a.id", "b.id"]),
+                    join("b", "c", JoinType::Cross, vec![]),
+                    join("c", "d", JoinType::Cross, vec![]),
                 ],
-                vec!["table1", "table2", "table3", "table4"]
-            ),(
-                "SELECT id, name from (SELECT * FROM users UNION SELECT * FROM customers)",
-                vec!["id", "name"],
-                vec!["customers", "users"]
+            ),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(inspect(sql, "generic").unwrap().joins, expected, "{sql}");
+        }
+    }
 
-        )];
+    #[test]
+    fn json_tables() {
+        let res = inspect(
+            "SELECT j.id, j.name FROM docs d, JSON_TABLE(d.body, '$[*]' COLUMNS (\
+             id INT PATH '$.id', name TEXT PATH '$.name', \
+             NESTED PATH '$.tags[*]' COLUMNS (tag TEXT PATH '$'))) AS j",
+            "mysql",
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["docs"]);
+        assert_eq!(res.columns, vec!["docs.body", "j.id", "j.name"]);
+        assert_eq!(
+            res.json_tables,
+            vec![JsonTable {
+                alias: Some("j".to_string()),
+                source: "docs.body".to_string(),
+                columns: vec!["id".to_string(), "name".to_string(), "tag".to_string()],
+            }]
+        );
+
+        let res = inspect(
+            "SELECT j.id FROM docs d CROSS APPLY OPENJSON(d.body) WITH (id INT '$.id', name NVARCHAR(50)) AS j",
+            "mssql",
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["docs"]);
+        assert_eq!(res.columns, vec!["docs.body", "j.id"]);
+        assert_eq!(
+            res.json_tables,
+            vec![JsonTable {
+                alias: Some("j".to_string()),
+                source: "docs.body".to_string(),
+                columns: vec!["id".to_string(), "name".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn target_schema() {
+        let tests = vec![
+            (
+                "INSERT INTO tenant_42.orders (id) VALUES (1)",
+                Some("tenant_42"),
+            ),
+            ("INSERT INTO orders (id) VALUES (1)", None),
+            ("UPDATE tenant_7.users SET age = 30", Some("tenant_7")),
+            (
+                "DELETE FROM app.tenant_1.users WHERE id = 1",
+                Some("tenant_1"),
+            ),
+            ("DELETE FROM users WHERE id = 1", None),
+            ("SELECT id FROM tenant_42.orders", None),
+        ];
+        for (sql, expected) in tests {
+            let res = inspect(sql, "generic").unwrap();
+            assert_eq!(res.target_schema.as_deref(), expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn update_subquery() {
+        let res = inspect(
+            "UPDATE t SET total = (SELECT SUM(amount) FROM line_items WHERE order_id = t.id)",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["line_items", "t"]);
+        assert_eq!(
+            res.columns,
+            vec![
+                "line_items.amount",
+                "line_items.order_id",
+                "t.id",
+                "t.total"
+            ]
+        );
+        assert_eq!(res.correlated_columns, vec!["t.id"]);
+
+        let res = inspect(
+            "UPDATE orders SET item_count = (SELECT COUNT(*) FROM items i WHERE i.order_id = orders.id) \
+             WHERE status = 'open'",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["items", "orders"]);
+        assert_eq!(res.correlated_columns, vec!["orders.id"]);
+        assert!(inspect("UPDATE t SET n = 1", "generic")
+            .unwrap()
+            .correlated_columns
+            .is_empty());
+    }
+
+    #[test]
+    fn functions() {
+        let tests = vec![
+            ("SELECT id FROM users", vec![], vec!["id"]),
+            (
+                "SELECT COUNT(user_id), max(orders.total) FROM orders",
+                vec!["COUNT", "MAX"],
+                vec!["orders.total", "user_id"],
+            ),
+            (
+                "SELECT SUM(CASE WHEN o.status = 'paid' THEN o.total ELSE 0 END) FROM orders o \
+                 WHERE LOWER(o.region) = 'eu'",
+                vec!["LOWER", "SUM"],
+                vec!["orders.region", "orders.status", "orders.total"],
+            ),
+            (
+                "SELECT id FROM users WHERE id IN (SELECT COALESCE(user_id, 0) FROM orders)",
+                vec!["COALESCE"],
+                vec!["id", "user_id"],
+            ),
+        ];
+        for (sql, functions, columns) in tests {
+            let res = inspect(sql, "generic").unwrap();
+            assert_eq!(res.functions, functions, "{sql}");
+            assert_eq!(res.columns, columns, "{sql}");
+        }
+    }
+
+    #[test]
+    fn native_api() {
+        let res = inspect("SELECT name, id FROM users WHERE age > 30", "generic").unwrap();
+        let copy = res.clone();
+        assert_eq!(copy.tables, vec!["users"]);
+        assert_eq!(copy.columns, vec!["age", "id", "name"]);
+        assert_eq!(copy.query_type, QueryType::SELECT);
+        assert_eq!(copy.predicate_count, res.predicate_count);
+    }
+
+    #[test]
+    fn case_keys() {
+        let res = inspect(
+            "SELECT COUNT(*) FROM orders GROUP BY CASE WHEN status = 'a' THEN 1 ELSE 2 END",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.columns, vec!["status"]);
+        assert_eq!(
+            res.group_by,
+            vec!["CASE WHEN status = 'a' THEN 1 ELSE 2 END"]
+        );
+
+        let res = inspect(
+            "SELECT o.id FROM orders o \
+             ORDER BY CASE o.priority WHEN 'high' THEN 0 ELSE o.rank END DESC",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(
+            res.columns,
+            vec!["orders.id", "orders.priority", "orders.rank"]
+        );
+        assert_eq!(
+            res.order_by,
+            vec![OrderByColumn {
+                column: "CASE o.priority WHEN 'high' THEN 0 ELSE o.rank END".to_string(),
+                asc: Some(false),
+                ordinal: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn schema_provider() {
+        // A provider computing the columns on demand, recording the lookups
+        struct Catalog {
+            lookups: std::cell::RefCell<Vec<String>>,
+        }
+        impl SchemaProvider for Catalog {
+            fn columns_of(&self, table: &str) -> Option<Vec<String>> {
+                self.lookups.borrow_mut().push(table.to_string());
+                match table {
+                    "users" => Some(vec!["id".to_string(), "name".to_string()]),
+                    _ => None,
+                }
+            }
+        }
+        let catalog = Catalog {
+            lookups: Default::default(),
+        };
+        let options = InspectOptions::default();
+        let res = inspect_with_provider(
+            "SELECT * FROM users WHERE id IN (SELECT user_id FROM orders)",
+            &catalog,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(res.columns, vec!["id", "user_id", "users.id", "users.name"]);
+        assert_eq!(catalog.lookups.borrow().as_slice(), ["users"]);
+
+        let res = inspect_with_provider("SELECT * FROM payments", &catalog, &options).unwrap();
+        assert_eq!(res.columns, vec!["*"]);
+        assert_eq!(
+            res.warnings,
+            vec![Warning::UnexpandedWildcard("payments".to_string())]
+        );
+
+        // A HashMap is a provider too
+        let schema = HashMap::from([("users".to_string(), vec!["id".to_string()])]);
+        let res = inspect_with_provider("SELECT * FROM users", &schema, &options).unwrap();
+        assert_eq!(res.columns, vec!["users.id"]);
+
+        // Like `inspect_with`, only the first statement is inspected, with the options
+        let options = InspectOptions {
+            dialect: Dialect::Postgres,
+            wildcard_policy: WildcardPolicy::Error,
+            ..Default::default()
+        };
+        let sql = "SELECT * FROM users; SELECT * FROM orders";
+        let res = inspect_with_provider(sql, &schema, &options).unwrap();
+        assert_eq!(res.tables, vec!["users"]);
+        assert!(matches!(
+            inspect_with_provider("SELECT * FROM orders", &schema, &options),
+            Err(InspectError::UnexpandedWildcard(_))
+        ));
+        let res = inspect_with_provider("REFRESH MATERIALIZED VIEW mv", &schema, &options).unwrap();
+        assert_eq!(res.query_type, QueryType::REFRESH);
+    }
+
+    #[test]
+    fn returning() {
+        let tests = vec![
+            ("INSERT INTO users (name) VALUES ('x')", vec![]),
+            (
+                "INSERT INTO users (name) VALUES ('x') RETURNING id, created_at",
+                vec!["id", "created_at"],
+            ),
+            (
+                "UPDATE users SET age = 30 WHERE id = 1 RETURNING *",
+                vec!["*"],
+            ),
+            (
+                "DELETE FROM users WHERE id = 1 RETURNING users.id, LOWER(name) AS lower_name",
+                vec!["users.id", "LOWER(name)"],
+            ),
+        ];
+        for (sql, expected) in tests {
+            let res = inspect(sql, "postgres").unwrap();
+            assert_eq!(res.returning, expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn resolved_targets() {
+        let views = HashMap::from([
+            (
+                "active_users".to_string(),
+                "SELECT id, name FROM users WHERE active = true".to_string(),
+            ),
+            (
+                "recent_active_users".to_string(),
+                "SELECT id FROM active_users WHERE created_at > '2024-01-01'".to_string(),
+            ),
+        ]);
+        let tests = vec![
+            ("DELETE FROM active_users WHERE id = 1", vec!["users"]),
+            ("UPDATE recent_active_users SET name = 'x'", vec!["users"]),
+            (
+                "INSERT INTO active_users (id, name) VALUES (1, 'x')",
+                vec!["users"],
+            ),
+            ("DELETE FROM users WHERE id = 1", vec![]),
+            ("SELECT id FROM active_users", vec![]),
+        ];
+        for (sql, expected) in tests {
+            let res = inspect_with_views(sql, &views).unwrap();
+            assert_eq!(res.resolved_targets, expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn camel_case_names() {
+        assert_eq!(camel_case("target_table"), "targetTable");
+        assert_eq!(camel_case("cte_dependencies"), "cteDependencies");
+        assert_eq!(camel_case("tables"), "tables");
+    }
+
+    #[test]
+    fn unresolved_columns() {
+        let tests = vec![
+            (
+                "SELECT address, name FROM table1 JOIN table2 ON table1.id = table2.id",
+                vec!["address", "name"],
+            ),
+            ("SELECT u.id, u.name FROM users u WHERE u.age > 30", vec![]),
+            // A single table: the columns belong to it
+            ("SELECT * FROM users WHERE age > 30", vec![]),
+            ("SELECT id FROM (SELECT id FROM users) sub", vec![]),
+            ("INSERT INTO users (id, name) VALUES (1, 'John')", vec![]),
+            (
+                "INSERT INTO logs (id) SELECT id FROM a JOIN b ON a.x = b.x",
+                vec!["id"],
+            ),
+            (
+                "SELECT id FROM users WHERE id IN (SELECT user_id FROM a, b)",
+                vec!["user_id"],
+            ),
+            (
+                "UPDATE t1 SET a = b FROM t2 WHERE t1.id = t2.id AND c > 0",
+                vec!["b", "c"],
+            ),
+            (
+                "DELETE FROM t1 USING t2 WHERE t1.id = t2.id AND c > 0",
+                vec!["c"],
+            ),
+        ];
+        for (sql, expected) in tests {
+            let res = inspect(sql, "postgres").unwrap();
+            assert_eq!(res.unresolved_columns, expected, "{sql}");
+        }
+
+        // The bare INSERT columns belong to the target
+        let options = InspectOptions {
+            qualify_insert_columns: false,
+            ..Default::default()
+        };
+        let res =
+            inspect_with("INSERT INTO users (id, name) VALUES (1, 'John')", &options).unwrap();
+        assert_eq!(res.columns, vec!["id", "name"]);
+        assert!(res.unresolved_columns.is_empty());
+    }
+
+    #[test]
+    fn schemas() {
+        let res = inspect(
+            "SELECT sales.public.orders.id FROM sales.public.orders",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["sales.public.orders"]);
+        assert_eq!(res.columns, vec!["sales.public.orders.id"]);
+        assert_eq!(res.schemas, vec!["public"]);
+        assert_eq!(res.databases, vec!["sales"]);
+
+        // An alias named like a schema doesn't capture the schema-qualified columns
+        let res = inspect(
+            "SELECT public.id, public.orders.total FROM public.orders JOIN users public ON public.id = 1",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.columns, vec!["public.orders.total", "users.id"]);
+        assert_eq!(res.schemas, vec!["public"]);
+
+        let res = inspect("SELECT id FROM users", "generic").unwrap();
+        assert!(res.schemas.is_empty());
+    }
+
+    #[test]
+    fn within_group() {
+        let res = inspect(
+            "SELECT endpoint, PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY response_time) \
+             FROM requests GROUP BY endpoint",
+            "postgres",
+        )
+        .unwrap();
+        assert_eq!(res.columns, vec!["endpoint", "response_time"]);
+        assert!(!res.invalid_aggregation);
+
+        let res = inspect(
+            "SELECT r.endpoint, PERCENTILE_DISC(0.9) WITHIN GROUP (ORDER BY r.latency DESC) \
+             FROM requests r",
+            "postgres",
+        )
+        .unwrap();
+        assert_eq!(res.columns, vec!["requests.endpoint", "requests.latency"]);
+        assert!(res.invalid_aggregation);
+    }
 
-        for (sql, columns, tables) in tests {
-            test_extract(sql, columns, tables, QueryType::SELECT);
+    #[test]
+    fn complexity_score() {
+        let tests = vec![
+            ("SELECT id FROM users", 0),
+            // 1 predicate
+            ("SELECT id FROM users WHERE age > 30", 1),
+            // 3 joins, 3 function calls
+            (
+                "SELECT t1.id, t1.label_real_address, t1.ext, COUNT(t2.contact_id), COUNT(t4.release_id)
+                FROM table1 t1
+                    LEFT JOIN table2 t2 ON t2.contact_type = 'lx' AND t2.contact_id = t1.id
+                    LEFT JOIN table3 t3 ON t3.id = t1.id
+                    LEFT JOIN table4 t4 ON t3.release_id = t4.release_id
+                GROUP BY t1.label_real_address
+                ORDER BY COUNT(t2.contact_id) DESC",
+                9,
+            ),
+            // 3 predicates, depth 2, 2 subqueries
+            (
+                "SELECT id FROM users WHERE id IN (SELECT user_id FROM orders \
+                 WHERE EXISTS (SELECT 1 FROM items WHERE items.order_id = orders.id))",
+                13,
+            ),
+        ];
+        for (sql, expected) in tests {
+            let res = inspect(sql, "generic").unwrap();
+            assert_eq!(res.complexity_score, expected, "{sql}");
         }
     }
 
     #[test]
-    fn insert() {
+    fn table_function_relations() {
+        // The relations without a plain table name must not panic
         let tests = vec![
             (
-                // simple
-                "INSERT INTO users (id, name) VALUES (1, 'Marco')",
-                vec!["users.id", "users.name"],
-                vec!["users"],
-            ), (
-                // multiple
-                "INSERT INTO Customers (CustomerName, ContactName, Address, City, PostalCode, Country)
-                    VALUES
-                    ('Platformatic', 'Luca', 'xxx 21', 'Vancouver', '4006', 'Canada'),
-                    ('Platformatic eu', 'Marco', 'yyy 23', 'Bologna', '40137', 'Italy');",
-                vec!["Customers.Address", "Customers.City", "Customers.ContactName", "Customers.Country", "Customers.CustomerName", "Customers.PostalCode"],
-                vec!["Customers"]
-            ), (
-                // without columns
-                "INSERT INTO Customers VALUES (5,'Harry', 'Potter', 31, 'Hogwarts');",
-                vec![],
-                vec!["Customers"]
+                "SELECT * FROM generate_series(1, 3)",
+                vec!["generate_series"],
+            ),
+            (
+                "SELECT j.key FROM docs d, LATERAL jsonb_each(d.body) j",
+                vec!["docs"],
+            ),
+            ("SELECT n FROM UNNEST(ARRAY[1, 2]) AS t(n)", vec![]),
+            (
+                "SELECT * FROM pg_catalog.generate_series(1, 3)",
+                vec!["pg_catalog.generate_series"],
             ),
-                (
-                "INSERT INTO Table1 (test_date, testno, examno, serialno, type, hours)
-                    SELECT T2.test_date, T4.testno, T2.examno, T2.serialno, type, (T3.started- T3.ended) as hours
-                    FROM Table2 T2, Table3 T3, Table4 T4
-                        Where T2.testno = T3.testno
-                        And T4.testno = 1
-                        and type = 'xxxxx'; ",
-                vec![
-                    "Table1.examno",
-                    "Table1.hours",
-                    "Table1.serialno",
-                    "Table1.test_date",
-                    "Table1.testno",
-                    "Table1.type",
-                    "Table2.examno",
-                    "Table2.serialno",
-                    "Table2.test_date",
-                    "Table2.testno",
-                    "Table3.ended",
-                    "Table3.started",
-                    "Table3.testno",
-                    "Table4.testno", 
-                    "type"
-                ],
-                vec!["Table1", "Table2", "Table3", "Table4"],
-            )
         ];
+        for (sql, expected) in tests {
+            let res = inspect(sql, "postgres").unwrap();
+            assert_eq!(res.tables, expected, "{sql}");
+        }
+    }
 
-        for (sql, columns, tables) in tests {
-            test_extract(sql, columns, tables, QueryType::INSERT);
+    #[test]
+    fn access() {
+        let tests = vec![
+            ("SELECT id FROM users", Access::Read),
+            ("WITH t AS (SELECT 1) SELECT * FROM t", Access::Read),
+            ("SELECT id FROM users FOR UPDATE", Access::Write),
+            ("INSERT INTO users (id) VALUES (1)", Access::Write),
+            ("UPDATE users SET age = 30", Access::Write),
+            ("DELETE FROM users WHERE age > 30", Access::Write),
+            ("CREATE VIEW v AS SELECT id FROM users", Access::Write),
+            ("CALL archive_users()", Access::Write),
+            // The unsupported statements may write
+            ("TRUNCATE users", Access::Write),
+            ("CREATE INDEX idx ON users (name)", Access::Write),
+            ("GRANT SELECT ON users TO reader", Access::Write),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(inspect(sql, "generic").unwrap().access, expected, "{sql}");
+        }
+        let res = inspect("TRUNCATE users", "generic").unwrap();
+        assert_eq!(res.query_type, QueryType::UNSUPPORTED);
+
+        // `SELECT ... INTO` creates a table
+        for (sql, dialect) in [
+            ("SELECT * INTO archive FROM orders", "postgres"),
+            ("SELECT id INTO #tmp FROM orders", "mssql"),
+        ] {
+            let res = inspect(sql, dialect).unwrap();
+            assert_eq!(res.query_type, QueryType::SELECT, "{sql}");
+            assert_eq!(res.access, Access::Write, "{sql}");
+            assert!(!res.idempotent, "{sql}");
         }
     }
 
     #[test]
-    fn delete() {
+    fn create_trigger() {
+        let sql = "CREATE TRIGGER audit_orders AFTER INSERT ON orders FOR EACH ROW EXECUTE FUNCTION log_order()";
+        let res = inspect(sql, "postgres").unwrap();
+        assert_eq!(res.query_type, QueryType::CREATE_TRIGGER);
+        assert_eq!(res.target_table, "orders");
+        assert_eq!(res.tables, vec!["orders"]);
+        assert_eq!(
+            query_type(sql, "generic").unwrap(),
+            QueryType::CREATE_TRIGGER
+        );
+
+        // The MsSql triggers have a body with statements
+        let sql = "CREATE TRIGGER audit_orders ON sales.orders AFTER INSERT AS BEGIN \
+                   UPDATE stats SET total = total + 1; \
+                   INSERT INTO audit (order_id) SELECT id FROM inserted; \
+                   END";
+        let res = inspect(sql, "mssql").unwrap();
+        assert_eq!(res.query_type, QueryType::CREATE_TRIGGER);
+        assert_eq!(res.target_table, "sales.orders");
+        assert_eq!(res.target_schema.as_deref(), Some("sales"));
+        assert_eq!(
+            res.tables,
+            vec!["audit", "inserted", "sales.orders", "stats"]
+        );
+        assert_eq!(
+            res.columns,
+            vec!["audit.order_id", "id", "stats.total", "total"]
+        );
+        assert_eq!(res.access, Access::Write);
+
+        // The options apply to the body too
+        let options = InspectOptions {
+            dialect: Dialect::MsSql,
+            fold_case: Casing::Upper,
+            qualify_insert_columns: false,
+            ..Default::default()
+        };
+        let res = inspect_with(sql, &options).unwrap();
+        assert_eq!(
+            res.tables,
+            vec!["AUDIT", "INSERTED", "SALES.ORDERS", "STATS"]
+        );
+        assert_eq!(res.columns, vec!["ID", "ORDER_ID", "STATS.TOTAL", "TOTAL"]);
+    }
+
+    #[test]
+    fn distinct() {
+        let res = inspect("SELECT id, name FROM users", "generic").unwrap();
+        assert!(!res.distinct);
+        assert!(res.distinct_on.is_empty());
+
+        let res = inspect("SELECT DISTINCT name FROM users", "generic").unwrap();
+        assert!(res.distinct);
+        assert!(res.distinct_on.is_empty());
+
+        let sql = "SELECT DISTINCT ON (u.name, LOWER(u.email)) u.name, u.email \
+                   FROM users u ORDER BY u.name, LOWER(u.email)";
+        let res = inspect(sql, "postgres").unwrap();
+        assert!(res.distinct);
+        assert_eq!(res.distinct_on, vec!["users.name", "LOWER(u.email)"]);
+
+        // Only the main query is considered
+        let sql = "SELECT id FROM users WHERE id IN (SELECT DISTINCT user_id FROM orders)";
+        assert!(!inspect(sql, "generic").unwrap().distinct);
+    }
+
+    #[test]
+    fn case_insensitive_dedup() {
+        let options = InspectOptions {
+            case_insensitive_dedup: true,
+            ..Default::default()
+        };
+        let sql = "SELECT Name, u.ID FROM users u JOIN USERS m ON u.id = m.manager_id \
+                   WHERE name = 'x'";
+        let res = inspect_with(sql, &options).unwrap();
+        assert_eq!(res.tables, vec!["users"]);
+        assert_eq!(res.columns, vec!["Name", "USERS.manager_id", "users.ID"]);
+
+        // The identifiers are kept as written by default
+        let res = inspect(sql, "generic").unwrap();
+        assert_eq!(res.tables, vec!["USERS", "users"]);
+        assert_eq!(
+            res.columns,
+            vec!["Name", "USERS.manager_id", "name", "users.ID", "users.id"]
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let sql = "MERGE INTO sales.orders t \
+                   USING (SELECT s.id, s.amount, r.region FROM staging s JOIN ref r ON s.ref_id = r.id) src \
+                   ON t.id = src.id \
+                   WHEN MATCHED THEN UPDATE SET t.amount = src.amount \
+                   WHEN NOT MATCHED THEN INSERT (id, amount) VALUES (src.id, src.amount)";
+        let res = inspect(sql, "snowflake").unwrap();
+        assert_eq!(res.query_type, QueryType::MERGE);
+        assert_eq!(res.target_table, "sales.orders");
+        assert_eq!(res.target_schema.as_deref(), Some("sales"));
+        // The source alias is not a table
+        assert_eq!(res.tables, vec!["ref", "sales.orders", "staging"]);
+        assert_eq!(
+            res.columns,
+            vec![
+                "ref.id",
+                "ref.region",
+                "sales.orders.amount",
+                "sales.orders.id",
+                "src.amount",
+                "src.id",
+                "staging.amount",
+                "staging.id",
+                "staging.ref_id"
+            ]
+        );
+        assert_eq!(
+            res.joins,
+            vec![JoinInfo {
+                left_table: "staging".to_string(),
+                right_table: "ref".to_string(),
+                join_type: JoinType::Inner,
+                columns: vec!["ref.id".to_string(), "staging.ref_id".to_string()],
+            }]
+        );
+        assert_eq!(res.access, Access::Write);
+        assert_eq!(query_type(sql, "generic").unwrap(), QueryType::MERGE);
+    }
+
+    #[test]
+    fn nested_aliases() {
+        // `x` is an alias of the alias `u`
+        let sql = "SELECT u.name FROM users u \
+                   WHERE EXISTS (SELECT 1 FROM u AS x WHERE x.manager_id = u.id)";
+        let res = inspect(sql, "generic").unwrap();
+        assert_eq!(
+            res.columns,
+            vec!["users.id", "users.manager_id", "users.name"]
+        );
+
+        // The cycles are not followed forever
+        let res = inspect("SELECT a.id, b.id FROM b AS a, a AS b", "generic").unwrap();
+        assert_eq!(res.columns, vec!["a.id", "b.id"]);
+    }
+
+    #[test]
+    fn filter_columns() {
         let tests = vec![
+            ("SELECT id FROM users", vec![]),
             (
-                // simple
-                "DELETE FROM users WHERE age > 30",
-                vec!["age"],
-                vec!["users"],
+                "SELECT id, name FROM users WHERE name = 'x' AND age > 18",
+                vec!["age", "name"],
             ),
             (
-                // With AND condition
-                "DELETE FROM Test
-                    WHERE Testno = 1
-                    AND TYPE = 'xxxxxx';",
-                vec!["TYPE", "Testno"],
-                vec!["Test"],
+                "SELECT u.id FROM users u JOIN orders o ON o.user_id = u.id WHERE o.total > 100 AND u.id = 1",
+                vec!["orders.total", "users.id"],
             ),
             (
-                // With EXISTS
-                "DELETE FROM t1
-                    WHERE t1.V1 > t1.V2
-                    AND EXISTS (SELECT * FROM t2 WHERE t2.V1 = t1.V1);",
-                vec!["t1.V1", "t1.V2", "t2.V1"],
-                vec!["t1", "t2"],
+                "SELECT id FROM users WHERE id IN (SELECT user_id FROM orders WHERE status = 'paid')",
+                vec!["id", "status"],
             ),
+            ("UPDATE users SET age = 30 WHERE id = 1", vec!["id"]),
+            ("DELETE FROM users WHERE deleted_at < NOW()", vec!["deleted_at"]),
         ];
+        for (sql, expected) in tests {
+            assert_eq!(
+                inspect(sql, "generic").unwrap().filter_columns,
+                expected,
+                "{sql}"
+            );
+        }
+    }
 
-        for (sql, columns, tables) in tests {
-            test_extract(sql, columns, tables, QueryType::DELETE);
+    #[test]
+    fn lineage() {
+        let pairs = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(t, s)| (t.to_string(), s.to_string()))
+                .collect()
+        };
+        let tests = vec![
+            (
+                "INSERT INTO t1 (a, b) SELECT x, y FROM t2",
+                pairs(&[("t1.a", "t2.x"), ("t1.b", "t2.y")]),
+            ),
+            (
+                "INSERT INTO t1 (a, b, c) SELECT s.x, UPPER(s.y), 1 FROM t2 s",
+                pairs(&[("t1.a", "t2.x"), ("t1.b", "UPPER(s.y)"), ("t1.c", "1")]),
+            ),
+            (
+                "INSERT INTO t1 (a, b) SELECT o.id, u.name FROM orders o JOIN users u ON o.user_id = u.id",
+                pairs(&[("t1.a", "orders.id"), ("t1.b", "users.name")]),
+            ),
+            // The counts don't match
+            ("INSERT INTO t1 (a) SELECT x, y FROM t2", pairs(&[("t1.a", "t2.x")])),
+            // The positions after the wildcard are unknown
+            (
+                "INSERT INTO t1 (a, b, c) SELECT x, * FROM t2",
+                pairs(&[("t1.a", "t2.x")]),
+            ),
+            ("INSERT INTO t1 SELECT x FROM t2", vec![]),
+            ("INSERT INTO t1 (a) VALUES (1)", vec![]),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(inspect(sql, "generic").unwrap().lineage, expected, "{sql}");
         }
     }
 
     #[test]
-    fn update() {
+    fn create_table() {
+        let sql = "CREATE TABLE sales.orders (id INT PRIMARY KEY, user_id INT REFERENCES users (id), total NUMERIC(10, 2))";
+        let res = inspect(sql, "generic").unwrap();
+        assert_eq!(res.query_type, QueryType::CREATE_TABLE);
+        assert_eq!(res.target_table, "sales.orders");
+        assert_eq!(res.target_schema.as_deref(), Some("sales"));
+        assert_eq!(res.tables, vec!["sales.orders"]);
+        assert_eq!(
+            res.columns,
+            vec![
+                "sales.orders.id",
+                "sales.orders.total",
+                "sales.orders.user_id"
+            ]
+        );
+        assert_eq!(res.column_types["sales.orders.total"], "NUMERIC(10,2)");
+        assert_eq!(res.access, Access::Write);
+        assert_eq!(query_type(sql, "generic").unwrap(), QueryType::CREATE_TABLE);
+
+        // The source of `CREATE TABLE ... AS SELECT`
+        let res = inspect(
+            "CREATE TABLE paid AS SELECT id, total FROM orders WHERE status = 'paid'",
+            "generic",
+        )
+        .unwrap();
+        assert_eq!(res.target_table, "paid");
+        assert_eq!(res.tables, vec!["orders", "paid"]);
+        assert_eq!(res.columns, vec!["id", "status", "total"]);
+    }
+
+    #[test]
+    fn alter_table() {
         let tests = vec![
             (
-                // simple
-                "UPDATE users SET age = 30",
+                "ALTER TABLE users ADD COLUMN email VARCHAR(255)",
+                vec!["users.email"],
+            ),
+            (
+                "ALTER TABLE users DROP COLUMN email, DROP COLUMN phone",
+                vec!["users.email", "users.phone"],
+            ),
+            (
+                "ALTER TABLE users RENAME COLUMN name TO full_name",
+                vec!["users.full_name", "users.name"],
+            ),
+            (
+                "ALTER TABLE users ALTER COLUMN age SET NOT NULL",
                 vec!["users.age"],
+            ),
+            ("ALTER TABLE users RENAME TO customers", vec![]),
+        ];
+        for (sql, columns) in tests {
+            let res = inspect(sql, "postgres").unwrap();
+            assert_eq!(res.query_type, QueryType::ALTER_TABLE, "{sql}");
+            assert_eq!(res.target_table, "users", "{sql}");
+            assert_eq!(res.columns, columns, "{sql}");
+        }
+
+        let res = inspect("ALTER TABLE public.users ADD COLUMN email TEXT", "postgres").unwrap();
+        assert_eq!(res.target_schema.as_deref(), Some("public"));
+        assert_eq!(res.column_types["public.users.email"], "TEXT");
+        let res = inspect("ALTER TABLE users RENAME TO customers", "postgres").unwrap();
+        assert_eq!(res.tables, vec!["customers", "users"]);
+    }
+
+    #[test]
+    fn drop() {
+        let res = inspect("DROP TABLE IF EXISTS sales.orders, users", "generic").unwrap();
+        assert_eq!(res.query_type, QueryType::DROP);
+        assert_eq!(res.object_type.as_deref(), Some("TABLE"));
+        assert_eq!(res.target_table, "sales.orders");
+        assert_eq!(res.target_schema.as_deref(), Some("sales"));
+        assert_eq!(res.tables, vec!["sales.orders", "users"]);
+        assert_eq!(res.access, Access::Write);
+
+        let res = inspect("DROP MATERIALIZED VIEW monthly_sales", "postgres").unwrap();
+        assert_eq!(res.object_type.as_deref(), Some("MATERIALIZED VIEW"));
+        assert_eq!(res.tables, vec!["monthly_sales"]);
+
+        // An index is not a table, the table it's defined on is
+        let res = inspect("DROP INDEX idx_email ON users", "mysql").unwrap();
+        assert_eq!(res.object_type.as_deref(), Some("INDEX"));
+        assert_eq!(res.target_table, "idx_email");
+        assert_eq!(res.tables, vec!["users"]);
+
+        assert!(inspect("SELECT 1", "generic")
+            .unwrap()
+            .object_type
+            .is_none());
+    }
+
+    #[test]
+    fn ordered() {
+        let options = InspectOptions {
+            ordered: true,
+            ..Default::default()
+        };
+        let sql = "SELECT u.name, o.total, u.id FROM users u JOIN orders o ON o.user_id = u.id \
+                   WHERE o.total > 100";
+        let res = inspect_with(sql, &options).unwrap();
+        assert_eq!(
+            res.columns,
+            vec!["users.name", "orders.total", "users.id", "orders.user_id"]
+        );
+        assert_eq!(res.tables, vec!["users", "orders"]);
+
+        // Sorted by default
+        let res = inspect(sql, "generic").unwrap();
+        assert_eq!(
+            res.columns,
+            vec!["orders.total", "orders.user_id", "users.id", "users.name"]
+        );
+        assert_eq!(res.tables, vec!["orders", "users"]);
+    }
+
+    #[test]
+    fn resolve_aliases() {
+        let sql = "SELECT u.name, o.total FROM users u JOIN orders o ON o.user_id = u.id";
+        let options = InspectOptions {
+            resolve_aliases: false,
+            ..Default::default()
+        };
+        let res = inspect_with(sql, &options).unwrap();
+        assert_eq!(res.columns, vec!["o.total", "o.user_id", "u.id", "u.name"]);
+        assert_eq!(res.tables, vec!["orders", "users"]);
+        assert_eq!(res.joins[0].left_table, "u");
+        assert_eq!(res.alias_map.len(), 2);
+
+        let res = inspect_with(sql, &InspectOptions::default()).unwrap();
+        assert_eq!(
+            res.columns,
+            vec!["orders.total", "orders.user_id", "users.id", "users.name"]
+        );
+    }
+
+    #[test]
+    fn parameters() {
+        let tests = vec![
+            ("SELECT id FROM users", vec![]),
+            (
+                "SELECT id FROM users WHERE age > ? AND name = ? LIMIT ?",
+                vec!["?", "?", "?"],
+            ),
+            (
+                "UPDATE users SET name = $2 WHERE id = $1 OR parent_id = $1",
+                vec!["$2", "$1", "$1"],
+            ),
+            (
+                "INSERT INTO users (id, name) VALUES (:id, :name)",
+                vec![":id", ":name"],
+            ),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(
+                inspect(sql, "generic").unwrap().parameters,
+                expected,
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn preserve_quotes() {
+        // The quotes are stripped the same way by every statement
+        let tests = vec![
+            ("SELECT \"Id\" FROM \"Users\"", vec!["Users"], vec!["Id"]),
+            (
+                "INSERT INTO \"Users\" (\"Id\") VALUES (1)",
+                vec!["Users"],
+                vec!["Users.Id"],
+            ),
+            (
+                "UPDATE \"Users\" u SET \"Name\" = 'x' WHERE u.\"Id\" = 1",
+                vec!["Users"],
+                vec!["Users.Id", "Users.Name"],
+            ),
+            (
+                "CREATE TABLE \"Users\" (\"Id\" INT)",
+                vec!["Users"],
+                vec!["Users.Id"],
+            ),
+        ];
+        for (sql, tables, columns) in tests {
+            let res = inspect(sql, "postgres").unwrap();
+            assert_eq!(res.tables, tables, "{sql}");
+            assert_eq!(res.columns, columns, "{sql}");
+        }
+
+        let options = InspectOptions {
+            dialect: Dialect::Postgres,
+            preserve_quotes: true,
+            ..Default::default()
+        };
+        let res = inspect_with(
+            "SELECT \"Id\", u.name FROM \"Users\" u JOIN Users m ON u.id = m.id",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["\"Users\"", "Users"]);
+        assert_eq!(
+            res.columns,
+            vec!["\"Id\"", "\"Users\".id", "\"Users\".name", "Users.id"]
+        );
+        let res = inspect_with("INSERT INTO \"Users\" (\"Id\") VALUES (1)", &options).unwrap();
+        assert_eq!(res.target_table, "\"Users\"");
+        assert_eq!(res.columns, vec!["\"Users\".\"Id\""]);
+
+        // Along with the case folding, only the unquoted identifiers are folded
+        let options = InspectOptions {
+            fold_case: Casing::Lower,
+            ..options
+        };
+        let res = inspect_with("SELECT \"Id\", Name FROM \"Users\"", &options).unwrap();
+        assert_eq!(res.columns, vec!["\"Id\"", "name"]);
+        let res = inspect_with(
+            "SELECT [Id] FROM [Users]",
+            &InspectOptions {
+                dialect: Dialect::MsSql,
+                preserve_quotes: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(res.tables, vec!["[Users]"]);
+        assert_eq!(res.columns, vec!["[Id]"]);
+    }
+
+    #[test]
+    fn upsert() {
+        let tests = vec![
+            (
+                "INSERT INTO users (id, name) VALUES (1, 'a') ON CONFLICT (id) DO UPDATE SET name = excluded.name, updated_at = now()",
+                "postgres",
+                vec!["excluded.name", "users.id", "users.name", "users.updated_at"],
+                true,
+            ),
+            (
+                "INSERT INTO users (id, name) VALUES (1, 'a') ON CONFLICT (email) DO NOTHING",
+                "postgres",
+                vec!["users.email", "users.id", "users.name"],
+                true,
+            ),
+            (
+                "INSERT INTO users (id, name) VALUES (1, 'a') ON DUPLICATE KEY UPDATE name = 'b', updated = NOW()",
+                "mysql",
+                vec!["users.id", "users.name", "users.updated"],
+                true,
+            ),
+            (
+                "INSERT INTO users (id, name) VALUES (1, 'a')",
+                "generic",
+                vec!["users.id", "users.name"],
+                false,
+            ),
+        ];
+        for (sql, dialect, columns, has_upsert) in tests {
+            let res = inspect(sql, dialect).unwrap();
+            assert_eq!(res.columns, columns, "{sql}");
+            assert_eq!(res.has_upsert, has_upsert, "{sql}");
+        }
+    }
+
+    #[test]
+    fn inspect_cache() {
+        let mut cache = InspectCache::new("postgres", 2);
+        let first = "SELECT id FROM users";
+        let expected = inspect(first, "postgres").unwrap();
+        assert_eq!(cache.inspect(first).unwrap().columns, expected.columns);
+        assert_eq!(cache.inspect(first).unwrap().tables, expected.tables);
+        assert_eq!(cache.len(), 1);
+
+        let second = "SELECT name FROM orders";
+        let third = "SELECT total FROM invoices";
+        cache.inspect(second).unwrap();
+        // `first` is now the most recently used, so `second` is evicted
+        cache.inspect(first).unwrap();
+        cache.inspect(third).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(first));
+        assert!(!cache.contains(second));
+        assert!(cache.contains(third));
+        // Inspected again, `second` evicts `first`
+        assert_eq!(cache.inspect(second).unwrap().tables, vec!["orders"]);
+        assert!(!cache.contains(first));
+        assert!(cache.contains(third));
+
+        // The errors are not cached
+        assert!(cache.inspect("SELEC id FROM users").is_err());
+        assert_eq!(cache.len(), 2);
+
+        let mut disabled = InspectCache::new("generic", 0);
+        assert_eq!(disabled.inspect(first).unwrap().tables, vec!["users"]);
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn having_columns() {
+        let tests = vec![
+            ("SELECT dept, COUNT(*) FROM users GROUP BY dept", vec![]),
+            (
+                "SELECT u.id, COUNT(o.id) FROM users u JOIN orders o ON o.user_id = u.id GROUP BY u.id HAVING COUNT(o.id) > 5",
+                vec!["orders.id"],
+            ),
+            (
+                "SELECT dept FROM users WHERE active GROUP BY dept HAVING AVG(salary) > 1000 AND dept <> 'hr'",
+                vec!["dept", "salary"],
+            ),
+        ];
+        for (sql, expected) in tests {
+            let res = inspect(sql, "generic").unwrap();
+            assert_eq!(res.having_columns, expected, "{sql}");
+            assert!(!res.filter_columns.contains(&"salary".to_string()), "{sql}");
+        }
+    }
+
+    #[test]
+    fn derived_tables() {
+        let tests = vec![
+            ("SELECT id FROM users", vec![], vec!["users"]),
+            (
+                "SELECT sub.x FROM (SELECT id AS x FROM users) sub",
+                vec!["sub"],
                 vec!["users"],
             ),
             (
-                // With AND condition
-                "UPDATE Test
-                    SET Test_Date = '2021-01-01'
-                    WHERE Testno = 1
-                    AND TYPE = 'xxxxxxx';",
-                vec!["TYPE", "Test.Test_Date", "Testno"],
-                vec!["Test"],
+                "SELECT s.x, o.total FROM (SELECT id AS x FROM users) AS s JOIN (SELECT * FROM orders) o ON o.user_id = s.x",
+                vec!["o", "s"],
+                vec!["orders", "users"],
+            ),
+        ];
+        for (sql, derived_tables, tables) in tests {
+            let res = inspect(sql, "generic").unwrap();
+            assert_eq!(res.derived_tables, derived_tables, "{sql}");
+            assert_eq!(res.tables, tables, "{sql}");
+        }
+    }
+
+    #[test]
+    fn window_functions() {
+        let tests = vec![
+            (
+                "SELECT dept, COUNT(*) FROM employees GROUP BY dept",
+                vec![],
+                vec!["dept"],
             ),
             (
-                // With EXISTS
-                "UPDATE t1
-                    SET t1.V1 = t2.V1
-                    WHERE t1.V1 > t1.V2
-                    AND EXISTS (SELECT * FROM t2 WHERE t2.V1 = t1.V1);",
-                vec!["t1.V1", "t1.V2", "t2.V1"],
-                vec!["t1", "t2"],
+                "SELECT name, ROW_NUMBER() OVER (PARTITION BY dept ORDER BY salary DESC) AS rn, UPPER(name) FROM employees",
+                vec!["ROW_NUMBER"],
+                vec!["dept", "name", "salary"],
             ),
             (
-                // Complex update
-                "UPDATE component SET name = p.number
-                       FROM part p 
-                       JOIN
-                           component_part cp ON p.id = cp.partId  JOIN
-                           component c ON cp.componentId = c.id
-                       WHERE p.brandId = 1003
-                       AND component.name='xxx'",
+                "SELECT e.name, SUM(e.amount) OVER w, RANK() OVER (ORDER BY e.hired_at) FROM employees e WINDOW w AS (PARTITION BY e.dept)",
+                vec!["RANK", "SUM"],
                 vec![
-                    "component.id",
-                    "component.name",
-                    "component_part.componentId",
-                    "component_part.partId",
-                    "part.brandId",
-                    "part.id",
-                    "part.number",
+                    "employees.amount",
+                    "employees.dept",
+                    "employees.hired_at",
+                    "employees.name",
                 ],
-                vec!["component", "component_part", "part"],
             ),
         ];
-
-        for (sql, columns, tables) in tests {
-            test_extract(sql, columns, tables, QueryType::UPDATE);
+        for (sql, window_functions, columns) in tests {
+            let res = inspect(sql, "generic").unwrap();
+            assert_eq!(res.window_functions, window_functions, "{sql}");
+            assert_eq!(res.columns, columns, "{sql}");
         }
     }
 }
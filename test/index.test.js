@@ -1,6 +1,6 @@
 const { test } = require('node:test')
 const { deepEqual } = require('node:assert')
-const { sqlinspector } = require('../pkg/sql_inspector')
+const { sqlinspector, sqlinspector_batch, sqlinspector_single } = require('../pkg/sql_inspector')
 
 // Minimal JS - WASM interop tests. The proper unit tests
 // for the inspector are in Rust.
@@ -10,7 +10,12 @@ test('simple selects', async () => {
     columns: ['id', 'name'],
     tables: ['users'],
     query_type: 'SELECT',
-    target_table: ''
+    target_table: '',
+    idempotent: true,
+    access: 'READ',
+    max_depth: 0,
+    predicate_count: 0,
+    complexity_score: 0
   }
   deepEqual(res, expected)
 
@@ -20,7 +25,13 @@ test('simple selects', async () => {
       columns: ['age', 'id', 'name'],
       tables: ['users'],
       query_type: 'SELECT',
-      target_table: ''
+      target_table: '',
+      idempotent: true,
+      access: 'READ',
+      max_depth: 0,
+      predicate_count: 1,
+      predicate_values: [['age', '30']],
+      complexity_score: 1
     }
     deepEqual(res, expected)
   }
@@ -32,7 +43,13 @@ test('simple selects', async () => {
       columns: ['*'],
       tables: ['users'],
       query_type: 'SELECT',
-      target_table: ''
+      target_table: '',
+      idempotent: true,
+      access: 'READ',
+      max_depth: 0,
+      predicate_count: 0,
+      alias_map: new Map([['u', 'users']]),
+      complexity_score: 0
     }
     deepEqual(res, expected)
   }
@@ -44,7 +61,12 @@ test('simple insert', async () => {
     columns: ['users.id', 'users.name'],
     tables: ['users'],
     query_type: 'INSERT',
-    target_table: 'users'
+    target_table: 'users',
+    idempotent: false,
+    access: 'WRITE',
+    max_depth: 0,
+    predicate_count: 0,
+    complexity_score: 0
   }
   deepEqual(res, expected)
 })
@@ -55,7 +77,12 @@ test('simple update', async () => {
     columns: ['users.age'],
     tables: ['users'],
     query_type: 'UPDATE',
-    target_table: 'users'
+    target_table: 'users',
+    idempotent: true,
+    access: 'WRITE',
+    max_depth: 0,
+    predicate_count: 0,
+    complexity_score: 0
   }
   deepEqual(res, expected)
 })
@@ -66,7 +93,72 @@ test('simple delete', async () => {
     columns: ['age'],
     tables: ['users'],
     query_type: 'DELETE',
-    target_table: ''
+    target_table: '',
+    idempotent: true,
+    access: 'WRITE',
+    max_depth: 0,
+    predicate_count: 1,
+    predicate_values: [['age', '30']],
+    complexity_score: 1
   }
   deepEqual(res, expected)
 })
+
+test('dialect', async () => {
+  const res = sqlinspector('SELECT `name` FROM `users`', 'mysql')
+  deepEqual(res.columns, ['name'])
+  deepEqual(res.tables, ['users'])
+})
+
+test('camelCase keys', async () => {
+  const res = sqlinspector('select * from users u', 'generic', { camelCase: true })
+  deepEqual(res, {
+    columns: ['*'],
+    tables: ['users'],
+    queryType: 'SELECT',
+    targetTable: '',
+    idempotent: true,
+    access: 'READ',
+    maxDepth: 0,
+    predicateCount: 0,
+    aliasMap: new Map([['u', 'users']]),
+    complexityScore: 0
+  })
+
+  const [batch] = sqlinspector_batch(['select name from users'], 'generic', { camelCase: true })
+  deepEqual(batch.queryType, 'SELECT')
+})
+
+test('parse error', async () => {
+  const res = sqlinspector('SELCT name FROM users')
+  deepEqual(Object.keys(res), ['error'])
+  deepEqual(res.error.near, 'SELCT')
+  deepEqual(typeof res.error.message, 'string')
+})
+
+test('batch', async () => {
+  const res = sqlinspector_batch(['select name from users', 'selct name from users'])
+  deepEqual(res[0], {
+    columns: ['name'],
+    tables: ['users'],
+    query_type: 'SELECT',
+    target_table: '',
+    idempotent: true,
+    access: 'READ',
+    max_depth: 0,
+    predicate_count: 0,
+    complexity_score: 0
+  })
+  deepEqual(Object.keys(res[1]), ['error'])
+  deepEqual(typeof res[1].error.message, 'string')
+})
+
+test('single', async () => {
+  deepEqual(sqlinspector_single('select name from users').tables, ['users'])
+  deepEqual(sqlinspector_single('select name from users; select id from orders'), {
+    error: { message: 'expected a single statement, found 2' }
+  })
+  const res = sqlinspector_single('select `name` from users', 'mysql', { camelCase: true })
+  deepEqual(res.queryType, 'SELECT')
+  deepEqual(res.columns, ['name'])
+})